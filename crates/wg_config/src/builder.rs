@@ -0,0 +1,104 @@
+use crate::{Client, Config, ConfigError, Drone, Server};
+use std::collections::HashMap;
+use wg_network::{NodeId, NodeType};
+
+/// Builds a [`Config`] from nodes and undirected links:
+/// `ConfigBuilder::new().drone(1, 0.05).drone(2, 0.1).client(3).link(1, 2).link(3, 1).build()`.
+///
+/// Links are added to the connections of both ends, listing the same link twice is harmless.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    nodes: Vec<(NodeId, NodeType, f32)>,
+    links: Vec<(NodeId, NodeId)>,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn drone(mut self, id: NodeId, pdr: f32) -> Self {
+        self.nodes.push((id, NodeType::Drone, pdr));
+        self
+    }
+    pub fn client(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeType::Client, 0.0));
+        self
+    }
+    pub fn server(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeType::Server, 0.0));
+        self
+    }
+    /// Adds the undirected link `a - b`.
+    pub fn link(mut self, a: NodeId, b: NodeId) -> Self {
+        self.links.push((a, b));
+        self
+    }
+    /// Adds the undirected links `a - b` for each `b`.
+    pub fn links(mut self, a: NodeId, others: impl IntoIterator<Item = NodeId>) -> Self {
+        self.links.extend(others.into_iter().map(|b| (a, b)));
+        self
+    }
+    /// Returns the config, sorted by id, if it follows all the rules checked by [`Config::validate`].
+    pub fn build(self) -> Result<Config, ConfigError> {
+        let mut node_types = HashMap::new();
+        for &(id, node_type, _) in self.nodes.iter() {
+            if node_types.insert(id, node_type).is_some() {
+                return Err(ConfigError::DuplicateId(id));
+            }
+        }
+
+        let mut neighbors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for (a, b) in self.links {
+            if a == b {
+                return Err(ConfigError::SelfLoop(a));
+            }
+            for (node, neighbor) in [(a, b), (b, a)] {
+                if !node_types.contains_key(&node) {
+                    return Err(ConfigError::UnknownNeighbor {
+                        node: neighbor,
+                        neighbor: node,
+                    });
+                }
+            }
+            if node_types[&a] != NodeType::Drone && node_types[&b] != NodeType::Drone {
+                return Err(ConfigError::NotADrone {
+                    node: a,
+                    neighbor: b,
+                });
+            }
+            for (node, neighbor) in [(a, b), (b, a)] {
+                let connected = neighbors.entry(node).or_default();
+                if !connected.contains(&neighbor) {
+                    connected.push(neighbor);
+                }
+            }
+        }
+
+        let mut config = Config {
+            drone: Vec::new(),
+            client: Vec::new(),
+            server: Vec::new(),
+        };
+        for (id, node_type, pdr) in self.nodes {
+            let connected = neighbors.remove(&id).unwrap_or_default();
+            match node_type {
+                NodeType::Drone => config.drone.push(Drone {
+                    id,
+                    connected_node_ids: connected,
+                    pdr,
+                }),
+                NodeType::Client => config.client.push(Client {
+                    id,
+                    connected_drone_ids: connected,
+                }),
+                NodeType::Server => config.server.push(Server {
+                    id,
+                    connected_drone_ids: connected,
+                }),
+            }
+        }
+        let config = config.sorted();
+        config.validate()?;
+        Ok(config)
+    }
+}
@@ -1,33 +1,65 @@
 #[cfg(feature = "serialize")]
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use wg_network::NodeId;
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serialize", derive(Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialize", serde(deny_unknown_fields))]
 pub struct Drone {
     pub id: NodeId,
     pub connected_node_ids: Vec<NodeId>,
+    #[cfg_attr(feature = "serialize", serde(serialize_with = "serialize_pdr"))]
     pub pdr: f32,
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serialize", derive(Deserialize))]
+/// Writes the pdr as the shortest decimal that reads back as the same `f32`,
+/// so `0.05` instead of `0.05000000074505806`.
+#[cfg(feature = "serialize")]
+fn serialize_pdr<S: Serializer>(pdr: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(pdr.to_string().parse().unwrap_or(*pdr as f64))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialize", serde(deny_unknown_fields))]
 pub struct Client {
     pub id: NodeId,
     pub connected_drone_ids: Vec<NodeId>,
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serialize", derive(Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialize", serde(deny_unknown_fields))]
 pub struct Server {
     pub id: NodeId,
     pub connected_drone_ids: Vec<NodeId>,
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serialize", derive(Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialize", serde(deny_unknown_fields))]
 pub struct Config {
     pub drone: Vec<Drone>,
     pub client: Vec<Client>,
     pub server: Vec<Server>,
 }
+
+impl Config {
+    /// Returns a copy of the config with the nodes and their connections sorted by id.
+    pub fn sorted(&self) -> Config {
+        let mut sorted = self.clone();
+        sorted.drone.sort_by_key(|drone| drone.id);
+        sorted.client.sort_by_key(|client| client.id);
+        sorted.server.sort_by_key(|server| server.id);
+        for drone in sorted.drone.iter_mut() {
+            drone.connected_node_ids.sort();
+        }
+        for client in sorted.client.iter_mut() {
+            client.connected_drone_ids.sort();
+        }
+        for server in sorted.server.iter_mut() {
+            server.connected_drone_ids.sort();
+        }
+        sorted
+    }
+}
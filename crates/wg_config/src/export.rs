@@ -0,0 +1,38 @@
+use crate::Config;
+use wg_network::{NetworkGraph, NodeType};
+
+impl From<&Config> for NetworkGraph {
+    fn from(config: &Config) -> Self {
+        let mut graph = NetworkGraph::new();
+        for drone in config.drone.iter() {
+            graph.add_node(drone.id, NodeType::Drone, Some(drone.pdr));
+            for &neighbor in drone.connected_node_ids.iter() {
+                graph.add_edge(drone.id, neighbor);
+            }
+        }
+        for client in config.client.iter() {
+            graph.add_node(client.id, NodeType::Client, None);
+            for &neighbor in client.connected_drone_ids.iter() {
+                graph.add_edge(client.id, neighbor);
+            }
+        }
+        for server in config.server.iter() {
+            graph.add_node(server.id, NodeType::Server, None);
+            for &neighbor in server.connected_drone_ids.iter() {
+                graph.add_edge(server.id, neighbor);
+            }
+        }
+        graph
+    }
+}
+
+impl Config {
+    /// Returns the network in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        NetworkGraph::from(self).to_dot()
+    }
+    /// Returns the network drawn as an SVG image.
+    pub fn to_svg(&self) -> String {
+        NetworkGraph::from(self).to_svg()
+    }
+}
@@ -0,0 +1,466 @@
+//! Generators of valid configs for the topologies commonly used in tests.
+//!
+//! Drones get the ids from 1 up, followed by the clients and then by the servers,
+//! so `Generator::double_chain(10).clients(2).servers(1).generate()` gives drones 1 to 10,
+//! clients 11 and 12 and server 13.
+
+use crate::{Client, Config, ConfigError, Drone, Server};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_network::NodeId;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Drones in a line.
+    Chain,
+    /// Two chains of the same length, with a link between the drones in the same position.
+    DoubleChain,
+    /// A chain closed on itself.
+    Ring,
+    /// A star polygon: each drone is connected to the ones a fixed number of positions away
+    /// (with 10 drones, the decagram).
+    Star,
+    /// Drones in rows of `columns` drones, connected to the ones next to them.
+    Grid { columns: usize },
+    /// Layers of drones, each connected to the drone below it and to the one crossing over.
+    Butterfly,
+    /// A tree where every drone has up to `branching` children.
+    Tree { branching: usize },
+    /// Random links with the given mean number of links per drone.
+    Random { mean_degree: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PdrDistribution {
+    Constant(f32),
+    /// Uniform between `min` and `max`, rounded to two decimals.
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+}
+
+impl PdrDistribution {
+    /// Returns true if every pdr drawn is between 0 and 1, and `min` is not above `max`.
+    pub fn is_valid(&self) -> bool {
+        let valid = |pdr: f32| (0.0..=1.0).contains(&pdr);
+        match *self {
+            PdrDistribution::Constant(pdr) => valid(pdr),
+            PdrDistribution::Uniform { min, max } => valid(min) && valid(max) && min <= max,
+        }
+    }
+}
+
+/// Which drones the clients and servers are attached to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attachment {
+    /// Consecutive drones, with the hosts spread evenly along the drone ids.
+    Spread,
+    /// Distinct random drones for each host.
+    Random,
+    /// Drones taken in order, cycling, from the given ones.
+    Drones(Vec<NodeId>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratorError {
+    NotEnoughDrones {
+        required: usize,
+        available: usize,
+    },
+    /// The nodes do not fit in [`NodeId`].
+    TooManyNodes(usize),
+    UnknownAttachmentDrone(NodeId),
+    /// The pdr distribution can draw values outside of `0..=1`, or NaN.
+    InvalidPdr(PdrDistribution),
+    /// The generated config is not valid, a bug of the generator.
+    Invalid(ConfigError),
+}
+
+impl Display for GeneratorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeneratorError::NotEnoughDrones {
+                required,
+                available,
+            } => write!(
+                f,
+                "{} drones are required, but only {} are available",
+                required, available
+            ),
+            GeneratorError::TooManyNodes(count) => {
+                write!(f, "{} nodes do not fit in the node id type", count)
+            }
+            GeneratorError::UnknownAttachmentDrone(id) => {
+                write!(f, "attachment drone {} does not exist", id)
+            }
+            GeneratorError::InvalidPdr(pdr) => {
+                write!(f, "pdr distribution {:?} is not between 0 and 1", pdr)
+            }
+            GeneratorError::Invalid(error) => write!(f, "invalid config generated: {}", error),
+        }
+    }
+}
+
+impl Error for GeneratorError {}
+
+/// Builds a [`Config`] of the given shape.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    shape: Shape,
+    drones: usize,
+    clients: usize,
+    servers: usize,
+    client_links: usize,
+    server_links: usize,
+    attachment: Attachment,
+    pdr: PdrDistribution,
+    seed: u64,
+}
+
+impl Generator {
+    // INITIALIZATION
+    /// Starts a generator of `drones` drones with one client and one server,
+    /// attached with [`Attachment::Spread`] to one and two drones respectively, and pdr 0.
+    pub fn new(shape: Shape, drones: usize) -> Self {
+        Self {
+            shape,
+            drones,
+            clients: 1,
+            servers: 1,
+            client_links: 1,
+            server_links: 2,
+            attachment: Attachment::Spread,
+            pdr: PdrDistribution::Constant(0.0),
+            seed: 0,
+        }
+    }
+    pub fn chain(drones: usize) -> Self {
+        Self::new(Shape::Chain, drones)
+    }
+    pub fn double_chain(drones: usize) -> Self {
+        Self::new(Shape::DoubleChain, drones)
+    }
+    pub fn ring(drones: usize) -> Self {
+        Self::new(Shape::Ring, drones)
+    }
+    pub fn star(drones: usize) -> Self {
+        Self::new(Shape::Star, drones)
+    }
+    pub fn grid(drones: usize, columns: usize) -> Self {
+        Self::new(Shape::Grid { columns }, drones)
+    }
+    pub fn butterfly(drones: usize) -> Self {
+        Self::new(Shape::Butterfly, drones)
+    }
+    pub fn tree(drones: usize, branching: usize) -> Self {
+        Self::new(Shape::Tree { branching }, drones)
+    }
+    pub fn random(drones: usize, mean_degree: f32) -> Self {
+        Self::new(Shape::Random { mean_degree }, drones)
+    }
+
+    // PARAMETERS
+    pub fn clients(mut self, clients: usize) -> Self {
+        self.clients = clients;
+        self
+    }
+    pub fn servers(mut self, servers: usize) -> Self {
+        self.servers = servers;
+        self
+    }
+    /// Sets the number of drones each client is connected to, clamped between 1 and 2.
+    pub fn client_links(mut self, links: usize) -> Self {
+        self.client_links = links.clamp(1, 2);
+        self
+    }
+    /// Sets the number of drones each server is connected to, at least 2.
+    pub fn server_links(mut self, links: usize) -> Self {
+        self.server_links = links.max(2);
+        self
+    }
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachment = attachment;
+        self
+    }
+    pub fn pdr(mut self, pdr: PdrDistribution) -> Self {
+        self.pdr = pdr;
+        self
+    }
+    /// Sets the seed used for random links, attachments and pdr.
+    /// The same parameters and seed always give the same config.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // GENERATION
+    pub fn generate(&self) -> Result<Config, GeneratorError> {
+        let total = self.drones + self.clients + self.servers;
+        if NodeId::try_from(total).is_err() {
+            return Err(GeneratorError::TooManyNodes(total));
+        }
+        if !self.pdr.is_valid() {
+            return Err(GeneratorError::InvalidPdr(self.pdr));
+        }
+        let required = match (self.clients, self.servers) {
+            (_, 1..) => self.server_links,
+            (1.., 0) => self.client_links,
+            (0, 0) => 0,
+        };
+        if self.drones < required {
+            return Err(GeneratorError::NotEnoughDrones {
+                required,
+                available: self.drones,
+            });
+        }
+        let id = |index: usize| NodeId::try_from(index + 1).unwrap();
+        let mut rng = SplitMix64(self.seed);
+
+        let mut links = self.drone_links(&mut rng);
+        Self::connect_components(self.drones, &mut links);
+        let mut drones = (0..self.drones)
+            .map(|i| Drone {
+                id: id(i),
+                connected_node_ids: Vec::new(),
+                pdr: self.sample_pdr(&mut rng),
+            })
+            .collect::<Vec<_>>();
+        for &(a, b) in links.iter() {
+            drones[a].connected_node_ids.push(id(b));
+            drones[b].connected_node_ids.push(id(a));
+        }
+
+        let hosts = self.clients + self.servers;
+        let mut attached = Vec::with_capacity(hosts);
+        for host in 0..hosts {
+            let links = if host < self.clients {
+                self.client_links
+            } else {
+                self.server_links
+            };
+            let drone_indexes = self.attach(host, hosts, links, &mut rng)?;
+            for &drone in drone_indexes.iter() {
+                drones[drone]
+                    .connected_node_ids
+                    .push(id(self.drones + host));
+            }
+            attached.push(drone_indexes.into_iter().map(id).collect::<Vec<_>>());
+        }
+        for drone in drones.iter_mut() {
+            drone.connected_node_ids.sort();
+        }
+        let mut attached = attached.into_iter();
+
+        let config = Config {
+            drone: drones,
+            client: (0..self.clients)
+                .map(|i| Client {
+                    id: id(self.drones + i),
+                    connected_drone_ids: attached.next().unwrap(),
+                })
+                .collect(),
+            server: (0..self.servers)
+                .map(|i| Server {
+                    id: id(self.drones + self.clients + i),
+                    connected_drone_ids: attached.next().unwrap(),
+                })
+                .collect(),
+        };
+        config.validate().map_err(GeneratorError::Invalid)?;
+        Ok(config)
+    }
+    /// Returns the links between drones, by index.
+    fn drone_links(&self, rng: &mut SplitMix64) -> BTreeSet<(usize, usize)> {
+        let n = self.drones;
+        let mut links = BTreeSet::new();
+        let mut link = |a: usize, b: usize| {
+            if a != b && a < n && b < n {
+                links.insert((a.min(b), a.max(b)));
+            }
+            links.len()
+        };
+        match self.shape {
+            Shape::Chain => (1..n).for_each(|i| {
+                link(i - 1, i);
+            }),
+            Shape::DoubleChain => {
+                let half = n.div_ceil(2);
+                for i in 1..half {
+                    link(i - 1, i);
+                    link(half + i - 1, half + i);
+                }
+                for i in 0..half {
+                    link(i, half + i);
+                }
+            }
+            Shape::Ring => {
+                for i in 0..n {
+                    link(i, (i + 1) % n);
+                }
+            }
+            Shape::Star => {
+                // the biggest step coprime with n draws a single star, 1 falls back to a ring
+                let step = (1..n.div_ceil(2))
+                    .rev()
+                    .find(|&step| gcd(step, n) == 1)
+                    .unwrap_or(1);
+                for i in 0..n {
+                    link(i, (i + step) % n);
+                }
+            }
+            Shape::Grid { columns } => {
+                let columns = columns.max(1);
+                for i in 0..n {
+                    if (i + 1) % columns != 0 {
+                        link(i, i + 1);
+                    }
+                    link(i, i + columns);
+                }
+            }
+            Shape::Butterfly => {
+                let mut width = 2;
+                while width * width * 2 <= n {
+                    width *= 2;
+                }
+                let bits = width.trailing_zeros() as usize;
+                for i in 0..n {
+                    let (layer, column) = (i / width, i % width);
+                    let next = (layer + 1) * width;
+                    link(i, next + column);
+                    link(i, next + (column ^ (1 << (layer % bits))));
+                }
+            }
+            Shape::Tree { branching } => {
+                for i in 1..n {
+                    link(i, (i - 1) / branching.max(1));
+                }
+            }
+            Shape::Random { mean_degree } => {
+                // a random spanning tree keeps the drones connected, then random links are added
+                let mut count = 0;
+                for i in 1..n {
+                    count = link(i, rng.below(i));
+                }
+                let target = ((mean_degree.max(0.0) * n as f32 / 2.0) as usize)
+                    .min(n * n.saturating_sub(1) / 2);
+                for _ in 0..target * 20 {
+                    if count >= target {
+                        break;
+                    }
+                    count = link(rng.below(n), rng.below(n));
+                }
+            }
+        }
+        links
+    }
+    /// Links the connected components of the drones, for the shapes that can leave them apart
+    /// when the number of drones does not fit them exactly.
+    fn connect_components(n: usize, links: &mut BTreeSet<(usize, usize)>) {
+        let mut component = (0..n).collect::<Vec<_>>();
+        fn root(component: &mut [usize], mut i: usize) -> usize {
+            while component[i] != i {
+                component[i] = component[component[i]];
+                i = component[i];
+            }
+            i
+        }
+        for &(a, b) in links.iter() {
+            let (root_a, root_b) = (root(&mut component, a), root(&mut component, b));
+            component[root_a] = root_b;
+        }
+        for i in 1..n {
+            let (root_previous, root_i) = (root(&mut component, i - 1), root(&mut component, i));
+            if root_previous != root_i {
+                links.insert((i - 1, i));
+                component[root_previous] = root_i;
+            }
+        }
+    }
+    /// Returns the indexes of the drones the host is attached to.
+    fn attach(
+        &self,
+        host: usize,
+        hosts: usize,
+        links: usize,
+        rng: &mut SplitMix64,
+    ) -> Result<Vec<usize>, GeneratorError> {
+        match &self.attachment {
+            Attachment::Spread => {
+                let start = host * self.drones / hosts.max(1);
+                Ok((0..links).map(|i| (start + i) % self.drones).collect())
+            }
+            Attachment::Random => {
+                let mut chosen = HashSet::new();
+                let mut drones = Vec::with_capacity(links);
+                while drones.len() < links {
+                    let drone = rng.below(self.drones);
+                    if chosen.insert(drone) {
+                        drones.push(drone);
+                    }
+                }
+                Ok(drones)
+            }
+            Attachment::Drones(ids) => {
+                let mut drones = Vec::with_capacity(links);
+                for i in 0..ids.len() {
+                    let id = ids[(host * links + i) % ids.len()];
+                    let index = (id as usize)
+                        .checked_sub(1)
+                        .filter(|&index| index < self.drones)
+                        .ok_or(GeneratorError::UnknownAttachmentDrone(id))?;
+                    if !drones.contains(&index) {
+                        drones.push(index);
+                    }
+                    if drones.len() == links {
+                        return Ok(drones);
+                    }
+                }
+                Err(GeneratorError::NotEnoughDrones {
+                    required: links,
+                    available: drones.len(),
+                })
+            }
+        }
+    }
+    fn sample_pdr(&self, rng: &mut SplitMix64) -> f32 {
+        match self.pdr {
+            PdrDistribution::Constant(pdr) => pdr,
+            PdrDistribution::Uniform { min, max } => {
+                let pdr = min + (max - min) * rng.unit();
+                (pdr * 100.0).round() / 100.0
+            }
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Small deterministic generator, so that a seed always gives the same config.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+    /// Returns a number in `0.0..1.0`.
+    fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,364 @@
+use crate::Config;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A format a config can be written in.
+/// Each is read only with its feature enabled: `toml`, `json` or `yaml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detects the format from the extension of the file:
+    /// `.toml`, `.json`, `.yaml` or `.yml`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ConfigFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+    /// Returns the feature that enables reading the format.
+    pub fn feature(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+            ConfigFormat::Yaml => "yaml",
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigFormat::Toml => write!(f, "TOML"),
+            ConfigFormat::Json => write!(f, "JSON"),
+            ConfigFormat::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+/// A config that could not be parsed, with the position of the error when known.
+/// `line` and `column` start from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub format: ConfigFormat,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: ", line, column)?,
+            (Some(line), None) => write!(f, "{}: ", line)?,
+            _ => {}
+        }
+        write!(f, "invalid {}: {}", self.format, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// A config file that could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The extension of the file is not one of those in [`ConfigFormat::from_path`].
+    UnknownFormat(PathBuf),
+    Parse {
+        path: PathBuf,
+        source: ParseError,
+    },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            LoadError::UnknownFormat(path) => write!(
+                f,
+                "cannot tell the format of {}, use .toml, .json, .yaml or .yml",
+                path.display()
+            ),
+            LoadError::Parse { path, source } if source.line.is_some() => {
+                write!(f, "{}:{}", path.display(), source)
+            }
+            LoadError::Parse { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::UnknownFormat(_) => None,
+            LoadError::Parse { source, .. } => Some(source),
+        }
+    }
+}
+
+impl Config {
+    // READING
+    /// Reads a config from a file, detecting its format from the extension.
+    /// Unknown keys are rejected, so that a misspelled field is not silently ignored.
+    ///
+    /// The config is not validated: call [`Config::validate`] to check it.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Config, LoadError> {
+        let path = path.as_ref();
+        let format =
+            ConfigFormat::from_path(path).ok_or_else(|| LoadError::UnknownFormat(path.into()))?;
+        let data = fs::read_to_string(path).map_err(|source| LoadError::Io {
+            path: path.into(),
+            source,
+        })?;
+        Config::parse(&data, format).map_err(|source| LoadError::Parse {
+            path: path.into(),
+            source,
+        })
+    }
+    /// Parses a config in the given format, rejecting unknown keys.
+    /// A format whose feature is disabled is reported as a [`ParseError`] without position.
+    #[cfg_attr(
+        not(any(feature = "toml", feature = "json", feature = "yaml")),
+        allow(unused_variables)
+    )]
+    pub fn parse(data: &str, format: ConfigFormat) -> Result<Config, ParseError> {
+        match format {
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => toml::from_str(data).map_err(|error| {
+                let (line, column) = match error.span() {
+                    Some(span) => {
+                        let (line, column) = line_column(data, span.start);
+                        (Some(line), Some(column))
+                    }
+                    None => (None, None),
+                };
+                ParseError {
+                    format,
+                    line,
+                    column,
+                    message: error.message().to_string(),
+                }
+            }),
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => serde_json::from_str(data).map_err(|error| {
+                let (line, column) = (error.line(), error.column());
+                let position = (line > 0).then_some((line, column));
+                ParseError {
+                    format,
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    message: strip_position(error.to_string(), position),
+                }
+            }),
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_norway::from_str(data).map_err(|error| {
+                let position = error
+                    .location()
+                    .map(|location| (location.line(), location.column()));
+                ParseError {
+                    format,
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    message: strip_position(error.to_string(), position),
+                }
+            }),
+            #[allow(unreachable_patterns)]
+            _ => Err(ParseError {
+                format,
+                line: None,
+                column: None,
+                message: format!(
+                    "support is disabled, enable the `{}` feature",
+                    format.feature()
+                ),
+            }),
+        }
+    }
+
+    // WRITING
+    #[cfg(feature = "toml")]
+    /// Returns the config in the layout of the Network Initialization File
+    /// (`[[drone]]`, then `[[client]]`, then `[[server]]`), sorted by id.
+    pub fn to_toml_string(&self) -> String {
+        toml::to_string(&self.sorted()).expect("a config is always serializable")
+    }
+    #[cfg(feature = "toml")]
+    /// Writes the config to a file, as returned by [`Config::to_toml_string`].
+    pub fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_toml_string())
+    }
+}
+
+/// Returns the line and column, starting from 1, of the byte at `offset`.
+#[cfg(feature = "toml")]
+fn line_column(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data[..offset.min(data.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Removes the " at line L column C" that JSON and YAML errors append to their message,
+/// since the position is reported separately.
+#[cfg(any(feature = "json", feature = "yaml"))]
+fn strip_position(message: String, position: Option<(usize, usize)>) -> String {
+    let Some((line, column)) = position else {
+        return message;
+    };
+    let suffix = format!(" at line {} column {}", line, column);
+    match message.strip_suffix(&suffix) {
+        Some(stripped) => stripped.to_string(),
+        None => message,
+    }
+}
+
+// run with `cargo test -p wg_config --all-features` to cover every format
+#[cfg(all(test, any(feature = "toml", feature = "json", feature = "yaml")))]
+mod tests {
+    use super::*;
+    use crate::{Client, Drone, Server};
+
+    fn config() -> Config {
+        Config {
+            drone: vec![
+                Drone {
+                    id: 1,
+                    connected_node_ids: vec![2, 4, 6],
+                    pdr: 0.05,
+                },
+                Drone {
+                    id: 2,
+                    connected_node_ids: vec![1, 6],
+                    pdr: 0.0,
+                },
+                Drone {
+                    id: 3,
+                    connected_node_ids: vec![],
+                    pdr: 1.0,
+                },
+            ],
+            client: vec![
+                Client {
+                    id: 4,
+                    connected_drone_ids: vec![1],
+                },
+                Client {
+                    id: 5,
+                    connected_drone_ids: vec![],
+                },
+            ],
+            server: vec![
+                Server {
+                    id: 6,
+                    connected_drone_ids: vec![1, 2],
+                },
+                Server {
+                    id: 7,
+                    connected_drone_ids: vec![],
+                },
+            ],
+        }
+    }
+
+    fn empty() -> Config {
+        Config {
+            drone: vec![],
+            client: vec![],
+            server: vec![],
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn toml_round_trip() {
+        for config in [config(), empty()] {
+            let written = config.to_toml_string();
+            let parsed = Config::parse(&written, ConfigFormat::Toml).unwrap();
+            assert_eq!(parsed, config);
+            assert_eq!(parsed.to_toml_string(), written);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn toml_is_written_sorted() {
+        let mut shuffled = config();
+        shuffled.drone.reverse();
+        shuffled.drone[0].connected_node_ids.reverse();
+        let written = shuffled.to_toml_string();
+        assert_eq!(written, config().to_toml_string());
+        assert_eq!(
+            Config::parse(&written, ConfigFormat::Toml).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_round_trip() {
+        for config in [config(), empty()] {
+            let written = serde_json::to_string_pretty(&config).unwrap();
+            let parsed = Config::parse(&written, ConfigFormat::Json).unwrap();
+            assert_eq!(parsed, config);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn yaml_round_trip() {
+        for config in [config(), empty()] {
+            let written = serde_norway::to_string(&config).unwrap();
+            let parsed = Config::parse(&written, ConfigFormat::Yaml).unwrap();
+            assert_eq!(parsed, config);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    fn formats_agree() {
+        let toml = config().to_toml_string();
+        let parsed = Config::parse(&toml, ConfigFormat::Toml).unwrap();
+        let json = serde_json::to_string(&parsed).unwrap();
+        let yaml = serde_norway::to_string(&parsed).unwrap();
+        for (data, format) in [(json, ConfigFormat::Json), (yaml, ConfigFormat::Yaml)] {
+            let parsed = Config::parse(&data, format).unwrap();
+            assert_eq!(parsed.to_toml_string(), toml);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn unknown_keys_are_rejected_with_position() {
+        let data = "[[drone]]\nid = 1\nconnected_node_ids = []\npdr = 0.1\nspeed = 3\n";
+        let error = Config::parse(data, ConfigFormat::Toml).unwrap_err();
+        assert_eq!(error.format, ConfigFormat::Toml);
+        assert_eq!(error.line, Some(5));
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn write_to_and_from_path() {
+        let path = std::env::temp_dir().join(format!("wg_config_{}.toml", std::process::id()));
+        config().write_to(&path).unwrap();
+        let read = Config::from_path(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), config());
+    }
+}
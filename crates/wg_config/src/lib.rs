@@ -1,3 +1,14 @@
+mod builder;
 mod config;
+mod export;
+pub mod generators;
+#[cfg(feature = "serialize")]
+mod io;
+mod topology;
+mod validation;
 
+pub use builder::*;
 pub use config::*;
+#[cfg(feature = "serialize")]
+pub use io::*;
+pub use validation::*;
@@ -0,0 +1,42 @@
+use crate::Config;
+use std::time::Instant;
+use wg_network::{NodeType, Topology};
+
+impl From<&Config> for Topology {
+    /// Returns the topology described by the config, with every edge seen now.
+    fn from(config: &Config) -> Self {
+        let now = Instant::now();
+        let mut topology = Topology::new();
+        for drone in config.drone.iter() {
+            topology.add_node(drone.id, NodeType::Drone);
+        }
+        for client in config.client.iter() {
+            topology.add_node(client.id, NodeType::Client);
+        }
+        for server in config.server.iter() {
+            topology.add_node(server.id, NodeType::Server);
+        }
+        let links = config
+            .drone
+            .iter()
+            .map(|drone| (drone.id, &drone.connected_node_ids))
+            .chain(
+                config
+                    .client
+                    .iter()
+                    .map(|client| (client.id, &client.connected_drone_ids)),
+            )
+            .chain(
+                config
+                    .server
+                    .iter()
+                    .map(|server| (server.id, &server.connected_drone_ids)),
+            );
+        for (id, connected_ids) in links {
+            for &neighbor in connected_ids.iter() {
+                topology.add_edge(id, neighbor, now);
+            }
+        }
+        topology
+    }
+}
@@ -0,0 +1,197 @@
+use crate::Config;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_network::{NodeId, NodeType};
+
+/// A violation of the rules of the Network Initialization File.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    DuplicateId(NodeId),
+    InvalidPdr {
+        drone: NodeId,
+        pdr: f32,
+    },
+    SelfLoop(NodeId),
+    RepeatedNeighbor {
+        node: NodeId,
+        neighbor: NodeId,
+    },
+    UnknownNeighbor {
+        node: NodeId,
+        neighbor: NodeId,
+    },
+    /// A client or a server is connected to something that is not a drone.
+    NotADrone {
+        node: NodeId,
+        neighbor: NodeId,
+    },
+    /// `from` lists `to` as a neighbor, but `to` does not list `from`.
+    AsymmetricLink {
+        from: NodeId,
+        to: NodeId,
+    },
+    /// A client must be connected to one or two drones.
+    ClientLinks {
+        client: NodeId,
+        count: usize,
+    },
+    /// A server must be connected to at least two drones.
+    ServerLinks {
+        server: NodeId,
+        count: usize,
+    },
+    /// The drone cannot be reached from the other drones without passing through clients or servers.
+    DisconnectedDrone(NodeId),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::DuplicateId(id) => write!(f, "id {} is used by more than one node", id),
+            ConfigError::InvalidPdr { drone, pdr } => {
+                write!(f, "drone {} has pdr {}, not between 0 and 1", drone, pdr)
+            }
+            ConfigError::SelfLoop(id) => write!(f, "node {} is connected to itself", id),
+            ConfigError::RepeatedNeighbor { node, neighbor } => {
+                write!(
+                    f,
+                    "node {} lists neighbor {} more than once",
+                    node, neighbor
+                )
+            }
+            ConfigError::UnknownNeighbor { node, neighbor } => {
+                write!(f, "node {} is connected to unknown node {}", node, neighbor)
+            }
+            ConfigError::NotADrone { node, neighbor } => write!(
+                f,
+                "node {} can only be connected to drones, but {} is not",
+                node, neighbor
+            ),
+            ConfigError::AsymmetricLink { from, to } => write!(
+                f,
+                "link {} - {} is listed by {} but not by {}",
+                from, to, from, to
+            ),
+            ConfigError::ClientLinks { client, count } => write!(
+                f,
+                "client {} is connected to {} drones instead of 1 or 2",
+                client, count
+            ),
+            ConfigError::ServerLinks { server, count } => write!(
+                f,
+                "server {} is connected to {} drones instead of at least 2",
+                server, count
+            ),
+            ConfigError::DisconnectedDrone(id) => {
+                write!(f, "drone {} is not connected to the other drones", id)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    /// Checks that the config follows all the rules of the Network Initialization File:
+    /// unique ids, valid pdr, bidirectional links without repetitions, clients connected
+    /// to 1 or 2 drones, servers to at least 2 drones and drones forming a connected graph.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut node_types = HashMap::new();
+        let mut neighbors = HashMap::new();
+        let nodes = self
+            .drone
+            .iter()
+            .map(|drone| (drone.id, NodeType::Drone, &drone.connected_node_ids))
+            .chain(
+                self.client
+                    .iter()
+                    .map(|client| (client.id, NodeType::Client, &client.connected_drone_ids)),
+            )
+            .chain(
+                self.server
+                    .iter()
+                    .map(|server| (server.id, NodeType::Server, &server.connected_drone_ids)),
+            );
+        for (id, node_type, connected_ids) in nodes {
+            if node_types.insert(id, node_type).is_some() {
+                return Err(ConfigError::DuplicateId(id));
+            }
+            neighbors.insert(id, connected_ids);
+        }
+
+        for drone in self.drone.iter() {
+            if !(0.0..=1.0).contains(&drone.pdr) {
+                return Err(ConfigError::InvalidPdr {
+                    drone: drone.id,
+                    pdr: drone.pdr,
+                });
+            }
+        }
+
+        let mut ids = node_types.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        for &id in ids.iter() {
+            let mut seen = HashSet::new();
+            for &neighbor in neighbors[&id].iter() {
+                if neighbor == id {
+                    return Err(ConfigError::SelfLoop(id));
+                }
+                if !seen.insert(neighbor) {
+                    return Err(ConfigError::RepeatedNeighbor { node: id, neighbor });
+                }
+                let Some(&neighbor_type) = node_types.get(&neighbor) else {
+                    return Err(ConfigError::UnknownNeighbor { node: id, neighbor });
+                };
+                if node_types[&id] != NodeType::Drone && neighbor_type != NodeType::Drone {
+                    return Err(ConfigError::NotADrone { node: id, neighbor });
+                }
+                if !neighbors[&neighbor].contains(&id) {
+                    return Err(ConfigError::AsymmetricLink {
+                        from: id,
+                        to: neighbor,
+                    });
+                }
+            }
+        }
+
+        for client in self.client.iter() {
+            let count = client.connected_drone_ids.len();
+            if !(1..=2).contains(&count) {
+                return Err(ConfigError::ClientLinks {
+                    client: client.id,
+                    count,
+                });
+            }
+        }
+        for server in self.server.iter() {
+            let count = server.connected_drone_ids.len();
+            if count < 2 {
+                return Err(ConfigError::ServerLinks {
+                    server: server.id,
+                    count,
+                });
+            }
+        }
+
+        // the drones alone must form a connected graph
+        let mut drones = self.drone.iter().map(|drone| drone.id).collect::<Vec<_>>();
+        drones.sort();
+        let Some(&first) = drones.first() else {
+            return Ok(());
+        };
+        let mut reached = HashSet::from([first]);
+        let mut queue = VecDeque::from([first]);
+        while let Some(id) = queue.pop_front() {
+            for &neighbor in neighbors[&id].iter() {
+                if node_types[&neighbor] == NodeType::Drone && reached.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        match drones.into_iter().find(|id| !reached.contains(id)) {
+            Some(id) => Err(ConfigError::DisconnectedDrone(id)),
+            None => Ok(()),
+        }
+    }
+}
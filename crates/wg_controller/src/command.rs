@@ -38,3 +38,31 @@ pub enum DroneEvent {
     PacketDropped(Packet),
     ControllerShortcut(Packet), //Used for direct routing of Ack, Nack and FloodResponse
 }
+
+/// From controller to client or server
+#[derive(Debug, Clone)]
+pub enum HostCommand {
+    RemoveSender(NodeId),
+    AddSender(NodeId, Sender<Packet>),
+}
+
+#[cfg(feature = "debug")]
+impl PartialEq for HostCommand {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (HostCommand::RemoveSender(node1), HostCommand::RemoveSender(node2)) => node1 == node2,
+            (HostCommand::AddSender(node1, sender1), HostCommand::AddSender(node2, sender2)) => {
+                node1 == node2 && sender1.same_channel(sender2)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// From client or server to controller
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum HostEvent {
+    PacketSent(Packet),
+    ControllerShortcut(Packet), //Used for direct routing of Ack, Nack and FloodResponse
+}
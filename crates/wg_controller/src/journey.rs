@@ -0,0 +1,235 @@
+use crate::DroneEvent;
+use std::collections::HashMap;
+use std::time::Instant;
+use wg_network::NodeId;
+use wg_packet::{NackType, Packet, PacketType};
+
+/// Identifies the journey of a fragment: `(session_id, source, fragment_index)`.
+pub type JourneyKey = (u64, NodeId, u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepKind {
+    /// The fragment was sent by the node to `to`.
+    Forwarded { to: NodeId },
+    /// The fragment was dropped by the node.
+    Dropped,
+    /// The node sent a Nack for the fragment.
+    Nacked(NackType),
+    /// The node (usually the destination) sent an Ack for the fragment.
+    Acked,
+    /// An Ack or Nack for the fragment was sent through the controller by the node.
+    Shortcut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JourneyStep {
+    pub node: NodeId,
+    pub at: Instant,
+    pub kind: StepKind,
+}
+
+/// Everything the controller saw happening to one fragment, in arrival order.
+#[derive(Debug, Clone)]
+pub struct Journey {
+    pub session_id: u64,
+    pub source: NodeId,
+    pub fragment_index: u64,
+    pub steps: Vec<JourneyStep>,
+}
+
+impl Journey {
+    pub fn new(session_id: u64, source: NodeId, fragment_index: u64) -> Self {
+        Self {
+            session_id,
+            source,
+            fragment_index,
+            steps: Vec::new(),
+        }
+    }
+    pub fn key(&self) -> JourneyKey {
+        (self.session_id, self.source, self.fragment_index)
+    }
+    /// Returns the nodes reached by the fragment and when they were reached.
+    /// Retransmissions show up as the same nodes visited again.
+    pub fn path(&self) -> Vec<(NodeId, Instant)> {
+        let mut path: Vec<(NodeId, Instant)> = Vec::new();
+        for step in self.steps.iter() {
+            if let StepKind::Forwarded { to } = step.kind {
+                if path.last().map(|&(id, _)| id) != Some(step.node) {
+                    path.push((step.node, step.at));
+                }
+                path.push((to, step.at));
+            }
+        }
+        path
+    }
+    /// Returns the nodes that dropped the fragment.
+    pub fn dropped_at(&self) -> Vec<NodeId> {
+        self.nodes_where(|kind| matches!(kind, StepKind::Dropped))
+    }
+    /// Returns the nodes that sent a Nack for the fragment, with its type.
+    pub fn nacked_at(&self) -> Vec<(NodeId, NackType)> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step.kind {
+                StepKind::Nacked(nack_type) => Some((step.node, nack_type)),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Returns the nodes that used the controller shortcut for this fragment.
+    pub fn shortcut_at(&self) -> Vec<NodeId> {
+        self.nodes_where(|kind| matches!(kind, StepKind::Shortcut))
+    }
+    /// Returns true if an Ack was sent for the fragment.
+    pub fn is_acked(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|step| matches!(step.kind, StepKind::Acked))
+    }
+    fn nodes_where(&self, predicate: impl Fn(&StepKind) -> bool) -> Vec<NodeId> {
+        self.steps
+            .iter()
+            .filter(|step| predicate(&step.kind))
+            .map(|step| step.node)
+            .collect()
+    }
+}
+
+/// Groups the controller events by fragment to rebuild their journeys.
+///
+/// Acks and Nacks are routed back to the source, so they are attributed to
+/// the journey whose source is the destination of their route.
+#[derive(Debug, Clone, Default)]
+pub struct JourneyTracker {
+    journeys: HashMap<JourneyKey, Journey>,
+}
+
+impl JourneyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // RECORDING
+    /// Records an event received now.
+    pub fn record(&mut self, event: &DroneEvent) {
+        self.record_at(event, Instant::now());
+    }
+    /// Records an event received at the given instant.
+    /// Events about flood packets are ignored.
+    pub fn record_at(&mut self, event: &DroneEvent, at: Instant) {
+        let Some((key, step)) = Self::step_of(event, at) else {
+            return;
+        };
+        self.journeys
+            .entry(key)
+            .or_insert_with(|| Journey::new(key.0, key.1, key.2))
+            .steps
+            .push(step);
+    }
+    fn step_of(event: &DroneEvent, at: Instant) -> Option<(JourneyKey, JourneyStep)> {
+        let (packet, shortcut) = match event {
+            DroneEvent::PacketSent(packet) | DroneEvent::PacketDropped(packet) => (packet, false),
+            DroneEvent::ControllerShortcut(packet) => (packet, true),
+        };
+        let header = &packet.routing_header;
+        let (source, fragment_index, node, kind) = match (&packet.pack_type, event) {
+            (PacketType::MsgFragment(fragment), DroneEvent::PacketDropped(_)) => (
+                header.source()?,
+                fragment.fragment_index,
+                header.current_hop()?,
+                StepKind::Dropped,
+            ),
+            (PacketType::MsgFragment(fragment), _) => (
+                header.source()?,
+                fragment.fragment_index,
+                header.previous_hop()?,
+                StepKind::Forwarded {
+                    to: header.current_hop()?,
+                },
+            ),
+            (PacketType::Ack(_) | PacketType::Nack(_), _) if shortcut => (
+                header.destination()?,
+                Self::acked_index(packet)?,
+                header.previous_hop().or(header.current_hop())?,
+                StepKind::Shortcut,
+            ),
+            // only the node creating the Ack or Nack matters, not the ones forwarding it
+            (PacketType::Ack(ack), _) if Self::is_created(packet) => (
+                header.destination()?,
+                ack.fragment_index,
+                header.source()?,
+                StepKind::Acked,
+            ),
+            (PacketType::Nack(nack), _) if Self::is_created(packet) => (
+                header.destination()?,
+                nack.fragment_index,
+                header.source()?,
+                StepKind::Nacked(nack.nack_type),
+            ),
+            _ => return None,
+        };
+        Some((
+            (packet.session_id, source, fragment_index),
+            JourneyStep { node, at, kind },
+        ))
+    }
+    fn acked_index(packet: &Packet) -> Option<u64> {
+        match &packet.pack_type {
+            PacketType::Ack(ack) => Some(ack.fragment_index),
+            PacketType::Nack(nack) => Some(nack.fragment_index),
+            _ => None,
+        }
+    }
+    fn is_created(packet: &Packet) -> bool {
+        packet.routing_header.previous_hop() == packet.routing_header.source()
+    }
+
+    // QUERIES
+    /// Returns the journey of the given fragment if any event was recorded for it.
+    pub fn journey(
+        &self,
+        session_id: u64,
+        source: NodeId,
+        fragment_index: u64,
+    ) -> Option<&Journey> {
+        self.journeys.get(&(session_id, source, fragment_index))
+    }
+    /// Returns an iterator over all the journeys.
+    pub fn journeys(&self) -> impl Iterator<Item = &Journey> {
+        self.journeys.values()
+    }
+    /// Returns all the journeys of a session, sorted by source and fragment index.
+    pub fn session(&self, session_id: u64) -> Vec<&Journey> {
+        self.sorted(|journey| journey.session_id == session_id)
+    }
+    /// Returns all the journeys of fragments dropped by the given node.
+    pub fn dropped_at(&self, node: NodeId) -> Vec<&Journey> {
+        self.sorted(|journey| journey.dropped_at().contains(&node))
+    }
+    /// Returns all the journeys of fragments nacked by the given node.
+    pub fn nacked_at(&self, node: NodeId) -> Vec<&Journey> {
+        self.sorted(|journey| journey.nacked_at().iter().any(|&(id, _)| id == node))
+    }
+    /// Returns all the journeys of fragments whose Ack or Nack was shortcut by the given node.
+    pub fn shortcut_at(&self, node: NodeId) -> Vec<&Journey> {
+        self.sorted(|journey| journey.shortcut_at().contains(&node))
+    }
+    /// Returns all the journeys that were not acked yet.
+    pub fn pending(&self) -> Vec<&Journey> {
+        self.sorted(|journey| !journey.is_acked())
+    }
+    /// Forgets all the journeys.
+    pub fn clear(&mut self) {
+        self.journeys.clear();
+    }
+    fn sorted(&self, predicate: impl Fn(&Journey) -> bool) -> Vec<&Journey> {
+        let mut journeys = self
+            .journeys
+            .values()
+            .filter(|journey| predicate(journey))
+            .collect::<Vec<_>>();
+        journeys.sort_by_key(|journey| journey.key());
+        journeys
+    }
+}
@@ -1,3 +1,7 @@
 mod command;
+mod journey;
+mod traffic;
 
 pub use command::*;
+pub use journey::*;
+pub use traffic::*;
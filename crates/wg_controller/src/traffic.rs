@@ -0,0 +1,53 @@
+use crate::DroneEvent;
+use std::collections::HashMap;
+use wg_network::{NetworkGraph, NodeId};
+
+/// Counts the packets crossing each link, from the `PacketSent` events.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficCounter {
+    links: HashMap<(NodeId, NodeId), u64>,
+    dropped: HashMap<NodeId, u64>,
+}
+
+impl TrafficCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Records an event, events without a valid routing header are ignored.
+    pub fn record(&mut self, event: &DroneEvent) {
+        match event {
+            DroneEvent::PacketSent(packet) => {
+                let header = &packet.routing_header;
+                if let (Some(a), Some(b)) = (header.previous_hop(), header.current_hop()) {
+                    *self.links.entry((a.min(b), a.max(b))).or_default() += 1;
+                }
+            }
+            DroneEvent::PacketDropped(packet) => {
+                if let Some(node) = packet.routing_header.current_hop() {
+                    *self.dropped.entry(node).or_default() += 1;
+                }
+            }
+            DroneEvent::ControllerShortcut(_) => {}
+        }
+    }
+    /// Returns the number of packets that crossed the link `a - b`, in any direction.
+    pub fn link_traffic(&self, a: NodeId, b: NodeId) -> u64 {
+        self.links.get(&(a.min(b), a.max(b))).cloned().unwrap_or(0)
+    }
+    /// Returns the number of packets dropped by the node.
+    pub fn dropped(&self, node: NodeId) -> u64 {
+        self.dropped.get(&node).cloned().unwrap_or(0)
+    }
+    /// Returns a copy of the graph with the traffic counted so far on its edges.
+    pub fn snapshot(&self, graph: &NetworkGraph) -> NetworkGraph {
+        let mut snapshot = graph.clone();
+        for (&(a, b), &count) in self.links.iter() {
+            snapshot.add_traffic(a, b, count);
+        }
+        snapshot
+    }
+    pub fn clear(&mut self) {
+        self.links.clear();
+        self.dropped.clear();
+    }
+}
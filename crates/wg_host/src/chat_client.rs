@@ -0,0 +1,405 @@
+use crate::{ServerDirectory, Transport, TransportEvent};
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_controller::HostCommand;
+use wg_message::{
+    ChatRequest, ChatResponse, CodecError, DroneSend, ServerType, ServerTypeRequest,
+    ServerTypeResponse,
+};
+use wg_network::NodeId;
+use wg_packet::Packet;
+
+/// A request of the application to a [`ChatClient`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum ChatCommand {
+    /// Registers to the chat server, answered with [`ChatEvent::Registered`].
+    /// The client registers again after every change of the topology.
+    Register(NodeId),
+    /// Asks the clients registered to the chat server, answered with [`ChatEvent::ClientList`].
+    ClientList(NodeId),
+    /// Sends a message to a client registered to the same chat server.
+    SendMessage {
+        server: NodeId,
+        to: NodeId,
+        message: String,
+    },
+    /// Discovers the network again, looking for new chat servers.
+    Discover,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatError {
+    /// A request to the server was given up.
+    Unreachable(NodeId),
+    /// The server answered `error_wrong_client_id!`.
+    WrongClientId(NodeId),
+    /// The server answered `error_unsupported_request!`.
+    Unsupported(NodeId),
+    /// The server answered with a response that does not match the request.
+    UnexpectedResponse(NodeId),
+    /// The response of the server cannot be reassembled or parsed.
+    InvalidResponse { server: NodeId, error: CodecError },
+    /// The request cannot be sent, such as a message over the size limit.
+    InvalidRequest(CodecError),
+}
+
+impl Display for ChatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatError::Unreachable(server) => write!(f, "server {} is unreachable", server),
+            ChatError::WrongClientId(server) => {
+                write!(f, "server {} does not know the client", server)
+            }
+            ChatError::Unsupported(server) => {
+                write!(f, "server {} does not support the request", server)
+            }
+            ChatError::UnexpectedResponse(server) => {
+                write!(f, "server {} sent an unexpected response", server)
+            }
+            ChatError::InvalidResponse { server, error } => {
+                write!(f, "invalid response from server {}: {}", server, error)
+            }
+            ChatError::InvalidRequest(error) => write!(f, "invalid request: {}", error),
+        }
+    }
+}
+
+impl Error for ChatError {}
+
+/// Something the application should know about, sent by a [`ChatClient`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum ChatEvent {
+    /// A server answered `server_type?` with [`ServerType::Chat`].
+    ServerFound(NodeId),
+    /// The client is registered to the server, with the clients registered so far.
+    Registered {
+        server: NodeId,
+        clients: Vec<NodeId>,
+    },
+    ClientList {
+        server: NodeId,
+        clients: Vec<NodeId>,
+    },
+    /// A message of another client, forwarded by the server.
+    MessageFrom {
+        server: NodeId,
+        from: NodeId,
+        message: String,
+    },
+    /// The server accepted the message and is forwarding it.
+    MessageSent { server: NodeId, to: NodeId },
+    /// The message was not accepted by the server, it is given back to the application.
+    MessageNotSent {
+        server: NodeId,
+        to: NodeId,
+        message: String,
+        error: ChatError,
+    },
+    /// A request other than a message failed.
+    Error(ChatError),
+}
+
+/// What a request in flight asked for.
+#[derive(Debug, Clone)]
+enum Pending {
+    ServerType,
+    Register,
+    ClientList,
+    SendMessage { to: NodeId, message: String },
+}
+
+/// A chat client on its own thread, exchanging messages with other clients through the
+/// chat servers of the network.
+///
+/// After every discovery round it asks `server_type?` to the servers not seen before and
+/// registers again to the servers it registered to, as they may have restarted or been
+/// unreachable. The application sends [`ChatCommand`]s, run once the servers are
+/// classified, and receives [`ChatEvent`]s, including the messages of other clients.
+pub struct ChatClient {
+    transport: Transport,
+    /// The servers that answered `server_type?`, chat or not.
+    servers: ServerDirectory,
+    registrations: BTreeSet<NodeId>,
+    /// Requests in flight by session id, with the server they were sent to.
+    requests: HashMap<u64, (NodeId, Pending)>,
+    commands: VecDeque<ChatCommand>,
+    controller_recv: Receiver<HostCommand>,
+    packet_recv: Receiver<Packet>,
+    command_recv: Receiver<ChatCommand>,
+    event_send: Sender<ChatEvent>,
+}
+
+impl ChatClient {
+    // INITIALIZATION
+    /// Creates a client sending through `transport`, usually of a [`wg_network::NodeType::Client`].
+    pub fn new(
+        transport: Transport,
+        controller_recv: Receiver<HostCommand>,
+        packet_recv: Receiver<Packet>,
+        command_recv: Receiver<ChatCommand>,
+        event_send: Sender<ChatEvent>,
+    ) -> Self {
+        Self {
+            transport,
+            servers: ServerDirectory::new(),
+            registrations: BTreeSet::new(),
+            requests: HashMap::new(),
+            commands: VecDeque::new(),
+            controller_recv,
+            packet_recv,
+            command_recv,
+            event_send,
+        }
+    }
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+    /// Returns the servers that answered `server_type?`, chat or not.
+    pub fn servers(&self) -> &ServerDirectory {
+        &self.servers
+    }
+    /// Returns the chat servers found, sorted.
+    pub fn chat_servers(&self) -> Vec<NodeId> {
+        self.servers.servers_of(&ServerType::Chat)
+    }
+    /// Returns the servers the client registered to.
+    pub fn registrations(&self) -> &BTreeSet<NodeId> {
+        &self.registrations
+    }
+
+    // RUN
+    /// Discovers the network and runs the commands of the application until the controller
+    /// or the application closes its channel, or the packet channel is closed.
+    pub fn run(&mut self) {
+        self.transport.discover();
+        loop {
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    let Ok(command) = command else {
+                        return;
+                    };
+                    self.transport.handle_command(command);
+                }
+                recv(self.command_recv) -> command => {
+                    let Ok(command) = command else {
+                        return;
+                    };
+                    self.commands.push_back(command);
+                }
+                recv(self.packet_recv) -> packet => {
+                    let Ok(packet) = packet else {
+                        return;
+                    };
+                    let events = self.transport.handle_packet(packet);
+                    self.handle_events(events);
+                }
+                default(self.transport.next_timeout()) => {}
+            }
+            let events = self.transport.tick();
+            self.handle_events(events);
+            self.run_commands();
+        }
+    }
+    /// Runs the commands waiting for the end of the discovery and classification of servers.
+    fn run_commands(&mut self) {
+        while self.is_ready() {
+            let Some(command) = self.commands.pop_front() else {
+                return;
+            };
+            match command {
+                ChatCommand::Register(server) => {
+                    self.registrations.insert(server);
+                    self.register(server);
+                }
+                ChatCommand::ClientList(server) => {
+                    self.send(server, Pending::ClientList, &ChatRequest::ClientList)
+                }
+                ChatCommand::SendMessage {
+                    server,
+                    to,
+                    message,
+                } => {
+                    let request = ChatRequest::SendMessage {
+                        from: self.transport.id(),
+                        to,
+                        message: message.clone(),
+                    };
+                    self.send(server, Pending::SendMessage { to, message }, &request);
+                }
+                ChatCommand::Discover => self.transport.discover(),
+            }
+        }
+    }
+    fn is_ready(&self) -> bool {
+        !self.transport.is_discovering() && !self.servers.is_classifying()
+    }
+    fn handle_events(&mut self, events: Vec<TransportEvent>) {
+        for event in events {
+            match event {
+                TransportEvent::Received {
+                    session_id,
+                    source_id,
+                    payload,
+                } => match self.take_request(session_id, source_id) {
+                    Some(pending) => self.handle_response(source_id, pending, &payload),
+                    None => self.handle_unsolicited(source_id, &payload),
+                },
+                TransportEvent::Corrupt {
+                    session_id,
+                    source_id,
+                    error,
+                } => {
+                    if let Some(pending) = self.take_request(session_id, source_id) {
+                        let error = ChatError::InvalidResponse {
+                            server: source_id,
+                            error,
+                        };
+                        self.fail(source_id, pending, error);
+                    }
+                }
+                TransportEvent::Undeliverable {
+                    session_id,
+                    destination,
+                } => {
+                    if let Some(pending) = self.take_request(session_id, destination) {
+                        self.fail(destination, pending, ChatError::Unreachable(destination));
+                    }
+                }
+                TransportEvent::TopologyChanged(_) => {
+                    self.classify_servers();
+                    let registrations = self.registrations.iter().cloned().collect::<Vec<_>>();
+                    for server in registrations {
+                        self.register(server);
+                    }
+                }
+                TransportEvent::Delivered { .. } | TransportEvent::DiscoveryFinished => {}
+            }
+        }
+    }
+
+    // REQUESTS
+    fn send(&mut self, server: NodeId, pending: Pending, request: &impl DroneSend) {
+        match self.transport.send_message(server, request) {
+            Ok(session_id) => {
+                self.requests.insert(session_id, (server, pending));
+            }
+            // only a message can be too big for the codec
+            Err(error) => self.fail(server, pending, ChatError::InvalidRequest(error)),
+        }
+    }
+    fn take_request(&mut self, session_id: u64, server: NodeId) -> Option<Pending> {
+        match self.requests.get(&session_id) {
+            Some((sent_to, _)) if *sent_to == server => self
+                .requests
+                .remove(&session_id)
+                .map(|(_, pending)| pending),
+            _ => None,
+        }
+    }
+    /// Asks `server_type?` to the servers not classified nor being classified.
+    fn classify_servers(&mut self) {
+        for server in self.servers.to_classify(self.transport.topology()) {
+            self.send(server, Pending::ServerType, &ServerTypeRequest);
+        }
+    }
+    /// Sends `Register` to the server, unless one is already in flight.
+    fn register(&mut self, server: NodeId) {
+        let registering = self
+            .requests
+            .values()
+            .any(|(to, pending)| *to == server && matches!(pending, Pending::Register));
+        if !registering {
+            let request = ChatRequest::Register(self.transport.id());
+            self.send(server, Pending::Register, &request);
+        }
+    }
+
+    // RESPONSES
+    fn handle_response(&mut self, server: NodeId, pending: Pending, payload: &[u8]) {
+        let codec = self.transport.codec();
+        if let Pending::ServerType = pending {
+            return match codec.decode(payload) {
+                Ok(ServerTypeResponse(server_type)) => {
+                    let is_chat = server_type == ServerType::Chat;
+                    self.servers.insert(server, server_type);
+                    if is_chat {
+                        self.emit(ChatEvent::ServerFound(server));
+                    }
+                }
+                Err(error) => self.fail(
+                    server,
+                    pending,
+                    ChatError::InvalidResponse { server, error },
+                ),
+            };
+        }
+        let response = match codec.decode(payload) {
+            Ok(response) => response,
+            Err(error) => {
+                return self.fail(
+                    server,
+                    pending,
+                    ChatError::InvalidResponse { server, error },
+                )
+            }
+        };
+        let event = match (pending, response) {
+            (Pending::Register, ChatResponse::ClientList(clients)) => {
+                ChatEvent::Registered { server, clients }
+            }
+            (Pending::ClientList, ChatResponse::ClientList(clients)) => {
+                ChatEvent::ClientList { server, clients }
+            }
+            (Pending::SendMessage { to, .. }, ChatResponse::MessageSent) => {
+                ChatEvent::MessageSent { server, to }
+            }
+            (pending, ChatResponse::WrongClientId) => {
+                return self.fail(server, pending, ChatError::WrongClientId(server))
+            }
+            (pending, ChatResponse::UnsupportedRequest) => {
+                return self.fail(server, pending, ChatError::Unsupported(server))
+            }
+            (pending, _) => {
+                return self.fail(server, pending, ChatError::UnexpectedResponse(server))
+            }
+        };
+        self.emit(event);
+    }
+    /// Delivers a message forwarded by a server, ignoring anything else.
+    fn handle_unsolicited(&mut self, server: NodeId, payload: &[u8]) {
+        if let Ok(ChatResponse::MessageFrom { from, message }) =
+            self.transport.codec().decode(payload)
+        {
+            self.emit(ChatEvent::MessageFrom {
+                server,
+                from,
+                message: String::from_utf8_lossy(&message).into_owned(),
+            });
+        }
+    }
+    fn fail(&mut self, server: NodeId, pending: Pending, error: ChatError) {
+        let event = match pending {
+            Pending::SendMessage { to, message } => ChatEvent::MessageNotSent {
+                server,
+                to,
+                message,
+                error,
+            },
+            Pending::ServerType => {
+                // asked again after the next discovery round that changes the topology
+                self.servers.failed(server);
+                ChatEvent::Error(error)
+            }
+            // a failed registration is sent again after the next change of the topology
+            _ => ChatEvent::Error(error),
+        };
+        self.emit(event);
+    }
+    fn emit(&self, event: ChatEvent) {
+        let _ = self.event_send.send(event);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::Server;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use wg_message::{ChatRequest, ChatResponse, ServerType};
+use wg_network::{NodeId, Topology};
+
+/// How many messages are kept for each client that cannot be reached, the oldest are
+/// dropped first.
+pub const MAX_QUEUED_MESSAGES: usize = 128;
+
+/// A [`Server`] relaying messages between the clients registered to it.
+///
+/// `SendMessage { to, .. }` is forwarded to `to` as `MessageFrom`, with the id of the
+/// client that actually sent it, and answered with `MessageSent`; a recipient that is not
+/// registered gets `WrongClientId` instead. Messages that cannot be delivered are queued,
+/// up to [`MAX_QUEUED_MESSAGES`] per client, and sent again after every discovery round
+/// that finds their recipient.
+#[derive(Debug, Clone, Default)]
+pub struct ChatServer {
+    clients: BTreeSet<NodeId>,
+    outgoing: Vec<(NodeId, ChatResponse)>,
+    queued: HashMap<NodeId, VecDeque<ChatResponse>>,
+}
+
+impl ChatServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the registered clients, sorted.
+    pub fn clients(&self) -> Vec<NodeId> {
+        self.clients.iter().cloned().collect()
+    }
+    pub fn is_registered(&self, client: NodeId) -> bool {
+        self.clients.contains(&client)
+    }
+    /// Returns how many messages are waiting for a route to the client.
+    pub fn queued(&self, client: NodeId) -> usize {
+        self.queued.get(&client).map_or(0, VecDeque::len)
+    }
+    fn queue(&mut self, client: NodeId, message: ChatResponse) {
+        let queue = self.queued.entry(client).or_default();
+        while queue.len() >= MAX_QUEUED_MESSAGES {
+            queue.pop_front();
+        }
+        queue.push_back(message);
+    }
+}
+
+impl Server for ChatServer {
+    type RequestType = ChatRequest;
+    type ResponseType = ChatResponse;
+
+    fn handle_request(&mut self, source_id: NodeId, request: ChatRequest) -> ChatResponse {
+        match request {
+            ChatRequest::ClientList => ChatResponse::ClientList(self.clients()),
+            // clients can only register themselves
+            ChatRequest::Register(id) if id != source_id => ChatResponse::WrongClientId,
+            ChatRequest::Register(id) => {
+                self.clients.insert(id);
+                ChatResponse::ClientList(self.clients())
+            }
+            ChatRequest::SendMessage { to, message, .. } => {
+                if !self.clients.contains(&to) {
+                    return ChatResponse::WrongClientId;
+                }
+                self.outgoing.push((
+                    to,
+                    ChatResponse::MessageFrom {
+                        from: source_id,
+                        message: message.into_bytes(),
+                    },
+                ));
+                ChatResponse::MessageSent
+            }
+        }
+    }
+
+    fn server_type(&self) -> ServerType {
+        ServerType::Chat
+    }
+
+    fn unsupported_request(&self) -> Option<ChatResponse> {
+        Some(ChatResponse::UnsupportedRequest)
+    }
+
+    fn take_outgoing(&mut self) -> Vec<(NodeId, ChatResponse)> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    fn on_undeliverable(
+        &mut self,
+        destination: NodeId,
+        _session_id: Option<u64>,
+        message: Option<ChatResponse>,
+    ) {
+        // only forwarded messages are worth sending again, not answers to old requests
+        if let Some(message @ ChatResponse::MessageFrom { .. }) = message {
+            self.queue(destination, message);
+        }
+    }
+
+    fn on_discovery_finished(&mut self, topology: &Topology) {
+        // the discovery only finds the nodes reachable from this server, a route that
+        // failed for a while can work again with the same nodes
+        let reachable = self
+            .queued
+            .keys()
+            .filter(|&&client| topology.contains_node(client))
+            .cloned()
+            .collect::<Vec<_>>();
+        for client in reachable {
+            if let Some(queue) = self.queued.remove(&client) {
+                self.outgoing
+                    .extend(queue.into_iter().map(|message| (client, message)));
+            }
+        }
+    }
+}
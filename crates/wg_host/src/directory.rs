@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use wg_message::file_id;
+
+/// The files of a directory served by a content server, by id.
+/// Hidden files, subdirectories and files with non UTF-8 names are skipped.
+#[derive(Debug, Clone)]
+pub(crate) struct ContentDirectory {
+    path: PathBuf,
+    files: BTreeMap<u64, PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl ContentDirectory {
+    pub(crate) fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut directory = Self {
+            path: path.into(),
+            files: BTreeMap::new(),
+            modified: None,
+        };
+        directory.reload()?;
+        Ok(directory)
+    }
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Scans the directory again.
+    pub(crate) fn reload(&mut self) -> io::Result<()> {
+        self.modified = fs::metadata(&self.path)?.modified().ok();
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+            if name.starts_with('.') || !entry.file_type()?.is_file() {
+                continue;
+            }
+            entries.push((name, entry.path()));
+        }
+        // the order of read_dir depends on the system, sorting keeps the ids stable
+        entries.sort();
+        let mut files = BTreeMap::new();
+        for (name, path) in entries {
+            // on the unlikely collision of two names the first by name wins
+            files.entry(file_id(&name)).or_insert(path);
+        }
+        self.files = files;
+        Ok(())
+    }
+    /// Scans the directory again if files were added, removed or renamed since the last scan.
+    /// Returns true if it was scanned.
+    pub(crate) fn refresh(&mut self) -> bool {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_some() && modified == self.modified {
+            return false;
+        }
+        self.reload().is_ok()
+    }
+    /// Returns the ids of the files, sorted.
+    pub(crate) fn ids(&self) -> Vec<u64> {
+        self.files.keys().cloned().collect()
+    }
+    pub(crate) fn file(&self, id: u64) -> Option<&Path> {
+        self.files.get(&id).map(PathBuf::as_path)
+    }
+}
@@ -0,0 +1,18 @@
+mod chat_client;
+mod chat_server;
+mod directory;
+mod media_server;
+mod server;
+mod server_directory;
+mod text_server;
+mod transport;
+mod web_browser;
+
+pub use chat_client::*;
+pub use chat_server::*;
+pub use media_server::*;
+pub use server::*;
+pub use server_directory::*;
+pub use text_server::*;
+pub use transport::*;
+pub use web_browser::*;
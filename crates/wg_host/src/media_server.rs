@@ -0,0 +1,123 @@
+use crate::directory::ContentDirectory;
+use crate::Server;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use wg_message::{Compression, MediaFile, MediaRequest, MediaResponse, ServerType};
+use wg_network::NodeId;
+
+/// A [`Server`] answering `media?(media_id)` with the files of a directory,
+/// such as images and audio, together with their MIME type.
+///
+/// Ids come from [`wg_message::file_id`] on the file name, the same used by text documents
+/// to reference media. Files are read only when requested, but then the file, its payload
+/// and its fragments are in memory together, and the fragments stay until acknowledged.
+/// Files of formats already compressed, such as most images, audio and video, are sent
+/// without compression.
+#[derive(Debug, Clone)]
+pub struct MediaServer {
+    directory: ContentDirectory,
+}
+
+impl MediaServer {
+    /// Creates a server for the files in `directory`, failing if it cannot be read.
+    pub fn new(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        Ok(Self {
+            directory: ContentDirectory::open(directory)?,
+        })
+    }
+    pub fn directory(&self) -> &Path {
+        self.directory.path()
+    }
+    /// Scans the directory again, even if it does not look changed.
+    pub fn reload(&mut self) -> io::Result<()> {
+        self.directory.reload()
+    }
+    /// Returns the ids of the files, sorted.
+    pub fn ids(&self) -> Vec<u64> {
+        self.directory.ids()
+    }
+    /// Reads the file, None if there is no such file or it cannot be read.
+    pub fn media(&self, id: u64) -> Option<MediaFile> {
+        let path = self.directory.file(id)?;
+        let data = fs::read(path).ok()?;
+        Some(MediaFile::new(Self::content_type(path), data))
+    }
+    /// Returns the MIME type of the file from its extension,
+    /// `application/octet-stream` if unknown.
+    pub fn content_type(path: &Path) -> &'static str {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "svg" => "image/svg+xml",
+            "bmp" => "image/bmp",
+            "ico" => "image/x-icon",
+            "mp3" => "audio/mpeg",
+            "wav" => "audio/wav",
+            "ogg" | "oga" => "audio/ogg",
+            "flac" => "audio/flac",
+            "m4a" => "audio/mp4",
+            "mp4" => "video/mp4",
+            "webm" => "video/webm",
+            "pdf" => "application/pdf",
+            "txt" => "text/plain",
+            _ => "application/octet-stream",
+        }
+    }
+    /// Returns true if files of the MIME type are already compressed,
+    /// so that deflate would not make them smaller.
+    pub fn is_compressed(content_type: &str) -> bool {
+        matches!(
+            content_type,
+            "image/png"
+                | "image/jpeg"
+                | "image/gif"
+                | "image/webp"
+                | "audio/mpeg"
+                | "audio/ogg"
+                | "audio/flac"
+                | "audio/mp4"
+                | "application/pdf"
+        ) || content_type.starts_with("video/")
+    }
+}
+
+impl Server for MediaServer {
+    type RequestType = MediaRequest;
+    type ResponseType = MediaResponse;
+
+    fn handle_request(&mut self, _source_id: NodeId, request: MediaRequest) -> MediaResponse {
+        self.directory.refresh();
+        match request {
+            MediaRequest::MediaList => MediaResponse::MediaList(self.ids()),
+            MediaRequest::Media(id) => match self.media(id) {
+                Some(media) => MediaResponse::Media(media),
+                None => MediaResponse::NotFound,
+            },
+        }
+    }
+
+    fn server_type(&self) -> ServerType {
+        ServerType::Media
+    }
+
+    fn compression(&self, response: &MediaResponse) -> Option<Compression> {
+        match response {
+            MediaResponse::Media(media) if Self::is_compressed(&media.content_type) => {
+                Some(Compression::None)
+            }
+            _ => None,
+        }
+    }
+
+    fn unsupported_request(&self) -> Option<MediaResponse> {
+        Some(MediaResponse::UnsupportedRequest)
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{Transport, TransportEvent, TransportOptions};
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use std::collections::HashMap;
+use wg_controller::{HostCommand, HostEvent};
+use wg_message::{
+    CodecError, Compression, Request, Response, ServerType, ServerTypeRequest, ServerTypeResponse,
+};
+use wg_network::{NodeId, NodeType, Topology};
+use wg_packet::Packet;
+
+/// The application logic of a server, run by a [`ServerRuntime`].
+pub trait Server {
+    type RequestType: Request;
+    type ResponseType: Response;
+
+    /// Answers a request of a client.
+    fn handle_request(
+        &mut self,
+        source_id: NodeId,
+        request: Self::RequestType,
+    ) -> Self::ResponseType;
+
+    /// Returns the type the runtime answers to [`ServerTypeRequest`], before any request is parsed.
+    fn server_type(&self) -> ServerType;
+
+    /// Returns the compression of the response, None for the one of the transport.
+    fn compression(&self, _response: &Self::ResponseType) -> Option<Compression> {
+        None
+    }
+    /// Returns the answer to a request that cannot be parsed, if any.
+    fn unsupported_request(&self) -> Option<Self::ResponseType> {
+        None
+    }
+    /// Returns the messages to send that do not answer a request, such as chat
+    /// messages forwarded to their recipient. Called after every request and event.
+    fn take_outgoing(&mut self) -> Vec<(NodeId, Self::ResponseType)> {
+        Vec::new()
+    }
+    /// Called when a message to `destination` was given up. `message` is the message
+    /// itself if it came from [`Server::take_outgoing`] or could not even be sent,
+    /// None if it answered a request. `session_id` is None if the message could not
+    /// even be sent, as when too large: the client then gets [`Server::unsupported_request`].
+    fn on_undeliverable(
+        &mut self,
+        _destination: NodeId,
+        _session_id: Option<u64>,
+        _message: Option<Self::ResponseType>,
+    ) {
+    }
+    /// Called after every discovery round that changed the topology.
+    fn on_topology_changed(&mut self, _topology: &Topology) {}
+    /// Called after every discovery round, changed topology or not, such as to retry
+    /// messages given up while a route was temporarily broken.
+    fn on_discovery_finished(&mut self, _topology: &Topology) {}
+}
+
+/// Runs a [`Server`] on its own thread: it owns the channels of the host, answers
+/// floods, discovers the network and serves many clients at once, since fragments
+/// are reassembled per `(session_id, source_id)`.
+///
+/// Every response is sent with the session id of its request and retransmitted until
+/// acknowledged, see [`Transport`].
+pub struct ServerRuntime<S: Server> {
+    server: S,
+    transport: Transport,
+    /// Messages from [`Server::take_outgoing`] not yet acknowledged.
+    unsolicited: HashMap<(u64, NodeId), S::ResponseType>,
+    controller_recv: Receiver<HostCommand>,
+    packet_recv: Receiver<Packet>,
+}
+
+impl<S: Server> ServerRuntime<S> {
+    // INITIALIZATION
+    pub fn new(
+        id: NodeId,
+        server: S,
+        controller_send: Sender<HostEvent>,
+        controller_recv: Receiver<HostCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        Self::with_options(
+            id,
+            server,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            TransportOptions::default(),
+        )
+    }
+    pub fn with_options(
+        id: NodeId,
+        server: S,
+        controller_send: Sender<HostEvent>,
+        controller_recv: Receiver<HostCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        options: TransportOptions,
+    ) -> Self {
+        Self {
+            server,
+            transport: Transport::new(id, NodeType::Server, controller_send, packet_send, options),
+            unsolicited: HashMap::new(),
+            controller_recv,
+            packet_recv,
+        }
+    }
+    pub fn server(&self) -> &S {
+        &self.server
+    }
+    pub fn server_mut(&mut self) -> &mut S {
+        &mut self.server
+    }
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    // RUN
+    /// Discovers the network and serves requests until the controller or the packet
+    /// channel is closed.
+    /// Commands of the controller are handled before packets.
+    pub fn run(&mut self) {
+        self.transport.discover();
+        loop {
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    let Ok(command) = command else {
+                        return;
+                    };
+                    self.transport.handle_command(command);
+                }
+                recv(self.packet_recv) -> packet => {
+                    let Ok(packet) = packet else {
+                        return;
+                    };
+                    let events = self.transport.handle_packet(packet);
+                    self.handle_events(events);
+                }
+                default(self.transport.next_timeout()) => {}
+            }
+            let events = self.transport.tick();
+            self.handle_events(events);
+        }
+    }
+    fn handle_events(&mut self, events: Vec<TransportEvent>) {
+        for event in events {
+            match event {
+                TransportEvent::Received {
+                    session_id,
+                    source_id,
+                    payload,
+                } => self.handle_message(session_id, source_id, &payload),
+                TransportEvent::Delivered {
+                    session_id,
+                    destination,
+                } => {
+                    self.unsolicited.remove(&(session_id, destination));
+                }
+                TransportEvent::Undeliverable {
+                    session_id,
+                    destination,
+                } => {
+                    let message = self.unsolicited.remove(&(session_id, destination));
+                    self.server
+                        .on_undeliverable(destination, Some(session_id), message);
+                }
+                TransportEvent::TopologyChanged(_) => {
+                    self.server.on_topology_changed(self.transport.topology())
+                }
+                TransportEvent::DiscoveryFinished => {
+                    self.server.on_discovery_finished(self.transport.topology())
+                }
+                TransportEvent::Corrupt { .. } => {}
+            }
+        }
+        for (destination, message) in self.server.take_outgoing() {
+            match self.transport.send_message(destination, &message) {
+                Ok(session_id) => {
+                    self.unsolicited.insert((session_id, destination), message);
+                }
+                Err(_) => self
+                    .server
+                    .on_undeliverable(destination, None, Some(message)),
+            }
+        }
+    }
+    fn handle_message(&mut self, session_id: u64, source_id: NodeId, payload: &[u8]) {
+        let codec = self.transport.codec();
+        if codec.decode::<ServerTypeRequest>(payload).is_ok() {
+            let response = ServerTypeResponse(self.server.server_type());
+            let sent = self
+                .transport
+                .codec_mut()
+                .serialize(&response)
+                .and_then(|payload| self.transport.reply(source_id, session_id, &payload));
+            if sent.is_err() {
+                self.server.on_undeliverable(source_id, None, None);
+            }
+            return;
+        }
+        let response = match codec.decode(payload) {
+            Ok(request) => Some(self.server.handle_request(source_id, request)),
+            Err(_) => self.server.unsupported_request(),
+        };
+        let Some(response) = response else {
+            return;
+        };
+        if self.respond(source_id, session_id, &response).is_ok() {
+            return;
+        }
+        // a response too big for the codec cannot be sent in any way, the client is told
+        // instead of waiting for it
+        self.server
+            .on_undeliverable(source_id, None, Some(response));
+        if let Some(fallback) = self.server.unsupported_request() {
+            if self.respond(source_id, session_id, &fallback).is_err() {
+                self.server
+                    .on_undeliverable(source_id, None, Some(fallback));
+            }
+        }
+    }
+    /// Serializes the response with the compression the server asks for and sends it.
+    fn respond(
+        &mut self,
+        source_id: NodeId,
+        session_id: u64,
+        response: &S::ResponseType,
+    ) -> Result<(), CodecError> {
+        let compression = self
+            .server
+            .compression(response)
+            .unwrap_or(self.transport.codec().compression());
+        let payload = self
+            .transport
+            .codec_mut()
+            .serialize_with(response, compression)?;
+        self.transport.reply(source_id, session_id, &payload)
+    }
+}
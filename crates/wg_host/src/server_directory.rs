@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+use wg_message::ServerType;
+use wg_network::{NodeId, NodeType, Topology};
+
+/// The servers of the network by type, built by a client from the answers to
+/// [`wg_message::ServerTypeRequest`] after every discovery round.
+#[derive(Debug, Clone, Default)]
+pub struct ServerDirectory {
+    servers: BTreeMap<NodeId, ServerType>,
+    /// Servers asked their type that did not answer yet.
+    asked: BTreeSet<NodeId>,
+}
+
+impl ServerDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the type of the server, None if it did not answer yet.
+    pub fn server_type(&self, server: NodeId) -> Option<&ServerType> {
+        self.servers.get(&server)
+    }
+    /// Returns the servers of the given type, sorted.
+    pub fn servers_of(&self, server_type: &ServerType) -> Vec<NodeId> {
+        self.servers
+            .iter()
+            .filter(|&(_, found)| found == server_type)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+    /// Returns the classified servers, sorted by id.
+    pub fn servers(&self) -> impl Iterator<Item = (NodeId, &ServerType)> + '_ {
+        self.servers
+            .iter()
+            .map(|(&id, server_type)| (id, server_type))
+    }
+    /// Returns true while some server did not answer its type.
+    pub fn is_classifying(&self) -> bool {
+        !self.asked.is_empty()
+    }
+
+    // CLASSIFICATION
+    /// Returns the servers of the topology to ask their type: those neither classified
+    /// nor already asked. They are considered asked from now on.
+    pub fn to_classify(&mut self, topology: &Topology) -> Vec<NodeId> {
+        let mut unknown = topology
+            .nodes()
+            .filter(|&(id, node_type)| {
+                node_type == NodeType::Server
+                    && !self.servers.contains_key(&id)
+                    && !self.asked.contains(&id)
+            })
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        unknown.sort();
+        self.asked.extend(unknown.iter().cloned());
+        unknown
+    }
+    /// Records the type answered by the server, returning true if it was not known.
+    pub fn insert(&mut self, server: NodeId, server_type: ServerType) -> bool {
+        self.asked.remove(&server);
+        self.servers.insert(server, server_type.clone()) != Some(server_type)
+    }
+    /// Records that the server did not answer, so it is asked again after the next discovery.
+    pub fn failed(&mut self, server: NodeId) {
+        self.asked.remove(&server);
+    }
+    pub fn remove(&mut self, server: NodeId) -> Option<ServerType> {
+        self.asked.remove(&server);
+        self.servers.remove(&server)
+    }
+}
@@ -0,0 +1,68 @@
+use crate::directory::ContentDirectory;
+use crate::Server;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use wg_message::{ServerType, TextRequest, TextResponse};
+use wg_network::NodeId;
+
+/// A [`Server`] answering `files_list?` and `file?(file_id)` with the files of a directory.
+///
+/// File ids come from [`wg_message::file_id`] on the file name, so they do not change
+/// across restarts. The directory is scanned again when files are added, removed or
+/// renamed, and files are read on every request, so edits are served right away.
+///
+/// Documents can embed media served by a media server by name, see
+/// [`wg_message::media_references`].
+#[derive(Debug, Clone)]
+pub struct TextServer {
+    directory: ContentDirectory,
+}
+
+impl TextServer {
+    /// Creates a server for the files in `directory`, failing if it cannot be read.
+    pub fn new(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        Ok(Self {
+            directory: ContentDirectory::open(directory)?,
+        })
+    }
+    pub fn directory(&self) -> &Path {
+        self.directory.path()
+    }
+    /// Scans the directory again, even if it does not look changed.
+    pub fn reload(&mut self) -> io::Result<()> {
+        self.directory.reload()
+    }
+    /// Returns the ids of the files, sorted.
+    pub fn ids(&self) -> Vec<u64> {
+        self.directory.ids()
+    }
+    /// Returns the content of the file, None if there is no such file or it is not text.
+    pub fn text(&self, id: u64) -> Option<String> {
+        fs::read_to_string(self.directory.file(id)?).ok()
+    }
+}
+
+impl Server for TextServer {
+    type RequestType = TextRequest;
+    type ResponseType = TextResponse;
+
+    fn handle_request(&mut self, _source_id: NodeId, request: TextRequest) -> TextResponse {
+        self.directory.refresh();
+        match request {
+            TextRequest::TextList => TextResponse::TextList(self.ids()),
+            TextRequest::Text(id) => match self.text(id) {
+                Some(text) => TextResponse::Text(text),
+                None => TextResponse::NotFound,
+            },
+        }
+    }
+
+    fn server_type(&self) -> ServerType {
+        ServerType::Text
+    }
+
+    fn unsupported_request(&self) -> Option<TextResponse> {
+        Some(TextResponse::UnsupportedRequest)
+    }
+}
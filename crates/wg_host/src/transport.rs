@@ -0,0 +1,791 @@
+use crossbeam_channel::Sender;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use wg_controller::{HostCommand, HostEvent};
+use wg_message::{
+    CodecError, Compression, DroneSend, MessageCodec, MessageEncoding, SessionKey,
+    DEFAULT_MAX_MESSAGE_SIZE,
+};
+use wg_network::{MergePolicy, NodeId, NodeType, SourceRoutingHeader, Topology, TopologyDiff};
+use wg_packet::{DiscoveryRound, Fragment, NackType, Packet, PacketType, RouteCache};
+
+/// How many received messages are remembered to ignore late retransmissions of their fragments.
+const COMPLETED_MEMORY: usize = 4096;
+
+/// Timeouts and limits of a [`Transport`].
+#[derive(Debug, Clone)]
+pub struct TransportOptions {
+    /// How long a discovery round waits for more flood responses.
+    pub discovery_quiet_period: Duration,
+    /// How long a fragment waits for its Ack or Nack before being sent again.
+    pub retransmission_timeout: Duration,
+    /// How many times a fragment can time out before its message is given up.
+    /// Fragments dropped by a drone do not count, as the Nack proves the route works.
+    pub max_timeouts: u32,
+    /// Limit on the size of the messages, sent and received.
+    pub max_message_size: usize,
+    /// Encoding of the messages sent, those received are decoded in any encoding.
+    pub encoding: MessageEncoding,
+    /// Compression of the messages sent, none by default.
+    /// Those received are decompressed if flagged, whatever this setting.
+    pub compression: Compression,
+    /// How many fragments can wait for their Ack towards a destination not seen before.
+    pub initial_window: usize,
+    /// Bounds of the window of unacknowledged fragments of a destination, see [`Transport`].
+    pub min_window: usize,
+    pub max_window: usize,
+    /// How many fragments of a single message can be unacknowledged, within the window
+    /// of its destination.
+    pub session_window: usize,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            discovery_quiet_period: Duration::from_millis(100),
+            retransmission_timeout: Duration::from_millis(500),
+            max_timeouts: 5,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            encoding: MessageEncoding::default(),
+            compression: Compression::default(),
+            initial_window: 16,
+            min_window: 4,
+            max_window: 512,
+            session_window: 128,
+        }
+    }
+}
+
+/// Something that happened in a [`Transport`] the host should know about.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum TransportEvent {
+    /// All the fragments of a message arrived.
+    Received {
+        session_id: u64,
+        source_id: NodeId,
+        payload: Vec<u8>,
+    },
+    /// A fragment could not be reassembled, the message it belonged to is discarded.
+    Corrupt {
+        session_id: u64,
+        source_id: NodeId,
+        error: CodecError,
+    },
+    /// All the fragments of a message were acknowledged.
+    Delivered {
+        session_id: u64,
+        destination: NodeId,
+    },
+    /// A message was given up, either because it timed out too many times or
+    /// because no route to the destination was found.
+    Undeliverable {
+        session_id: u64,
+        destination: NodeId,
+    },
+    /// A discovery round finished, with the changes to the topology.
+    TopologyChanged(TopologyDiff),
+    /// A discovery round finished, whether it changed the topology or not.
+    /// It follows [`TransportEvent::TopologyChanged`] when both are emitted.
+    DiscoveryFinished,
+}
+
+/// A message being sent, keyed by `(session_id, destination)`.
+#[derive(Debug, Clone)]
+struct Outgoing {
+    fragments: Vec<Fragment>,
+    acked: Vec<bool>,
+    sent_at: Vec<Option<Instant>>,
+    timeouts: u32,
+    /// None while waiting for a route.
+    route: Option<SourceRoutingHeader>,
+}
+
+impl Outgoing {
+    /// Returns the fragments sent and not acknowledged yet.
+    fn in_flight(&self) -> usize {
+        (0..self.fragments.len())
+            .filter(|&index| !self.acked[index] && self.sent_at[index].is_some())
+            .count()
+    }
+    /// Returns the fragments to send: never sent or lost.
+    fn waiting(&self) -> VecDeque<usize> {
+        (0..self.fragments.len())
+            .filter(|&index| !self.acked[index] && self.sent_at[index].is_none())
+            .collect()
+    }
+    /// Marks the fragments not acknowledged as lost, to be sent again.
+    fn lose_all(&mut self) {
+        for (sent_at, &acked) in self.sent_at.iter_mut().zip(self.acked.iter()) {
+            if !acked {
+                *sent_at = None;
+            }
+        }
+    }
+}
+
+/// The congestion window of a destination: how many fragments of its messages can be
+/// sent and not acknowledged yet.
+#[derive(Debug, Clone)]
+struct Window {
+    size: f64,
+    /// When the window last shrank, fragments sent before cannot shrink it again.
+    shrunk_at: Option<Instant>,
+    /// Rotates the session served first, so that sessions take turns.
+    turn: usize,
+}
+
+/// The packet level of a client or server: it sends messages as fragments over
+/// routes computed on the discovered topology, retransmitting them until acknowledged,
+/// and reassembles the messages it receives, acknowledging every fragment.
+///
+/// Fragments are sent within a window per destination, shared in turns between the
+/// messages to it, so a large message does not delay the others nor flood the drones.
+/// A message gets at most its share of the window, and no more than
+/// [`TransportOptions::session_window`] fragments.
+/// The window grows by one fragment for every window acknowledged and halves when a
+/// fragment is dropped, down to the minimum when fragments time out (AIMD).
+///
+/// It also answers flood requests and runs discovery rounds, at start and whenever
+/// a route breaks. It does not own the receiving channels: the host calls
+/// [`Transport::handle_packet`], [`Transport::handle_command`] and, at least every
+/// [`Transport::next_timeout`], [`Transport::tick`].
+#[derive(Debug)]
+pub struct Transport {
+    id: NodeId,
+    node_type: NodeType,
+    options: TransportOptions,
+    controller_send: Sender<HostEvent>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    codec: MessageCodec,
+    topology: Topology,
+    routes: RouteCache,
+    discovery: Option<DiscoveryRound>,
+    next_flood_id: u64,
+    next_session_id: u64,
+    outgoing: HashMap<(u64, NodeId), Outgoing>,
+    windows: HashMap<NodeId, Window>,
+    completed: HashSet<SessionKey>,
+    completed_order: VecDeque<SessionKey>,
+}
+
+impl Transport {
+    // INITIALIZATION
+    pub fn new(
+        id: NodeId,
+        node_type: NodeType,
+        controller_send: Sender<HostEvent>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        options: TransportOptions,
+    ) -> Self {
+        let mut topology = Topology::new();
+        topology.add_node(id, node_type);
+        let mut codec = MessageCodec::with_encoding(options.max_message_size, options.encoding);
+        codec.set_compression(options.compression);
+        Self {
+            id,
+            node_type,
+            codec,
+            options,
+            controller_send,
+            packet_send,
+            topology,
+            routes: RouteCache::new(),
+            discovery: None,
+            next_flood_id: 0,
+            next_session_id: 0,
+            outgoing: HashMap::new(),
+            windows: HashMap::new(),
+            completed: HashSet::new(),
+            completed_order: VecDeque::new(),
+        }
+    }
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+    pub fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+    pub fn options(&self) -> &TransportOptions {
+        &self.options
+    }
+    pub fn codec(&self) -> &MessageCodec {
+        &self.codec
+    }
+    /// Returns the codec to serialize messages with, see [`MessageCodec::serialize`].
+    pub fn codec_mut(&mut self) -> &mut MessageCodec {
+        &mut self.codec
+    }
+    /// Returns the topology found by the last discovery round, minus the broken links seen since.
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+    /// Returns the ids of the neighbors the host can send to.
+    pub fn neighbors(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.packet_send.keys().cloned()
+    }
+    /// Returns the number of messages not yet fully acknowledged.
+    pub fn pending(&self) -> usize {
+        self.outgoing.len()
+    }
+    /// Returns how many fragments to the destination can be unacknowledged at once,
+    /// None if nothing was sent to it yet.
+    pub fn window(&self, destination: NodeId) -> Option<usize> {
+        self.windows
+            .get(&destination)
+            .map(|window| window.size as usize)
+    }
+    /// Returns true while a discovery round is waiting for flood responses.
+    pub fn is_discovering(&self) -> bool {
+        self.discovery.is_some()
+    }
+
+    // SENDING
+    /// Sends a new message, returning its session id.
+    /// Session ids start with the id of the host, so they never clash with those of other hosts.
+    pub fn send(&mut self, destination: NodeId, payload: &[u8]) -> Result<u64, CodecError> {
+        let session_id = (u64::from(self.id) << 32) | (self.next_session_id & u64::from(u32::MAX));
+        self.next_session_id += 1;
+        self.reply(destination, session_id, payload)?;
+        Ok(session_id)
+    }
+    /// Sends a message with the given session id, usually the one of the request it answers.
+    pub fn reply(
+        &mut self,
+        destination: NodeId,
+        session_id: u64,
+        payload: &[u8],
+    ) -> Result<(), CodecError> {
+        let fragments = self.codec.fragment_payload(payload)?;
+        let count = fragments.len();
+        self.outgoing.insert(
+            (session_id, destination),
+            Outgoing {
+                fragments,
+                acked: vec![false; count],
+                sent_at: vec![None; count],
+                timeouts: 0,
+                route: None,
+            },
+        );
+        self.pump(destination);
+        Ok(())
+    }
+    /// Serializes the content with the codec and sends it as a new message, see [`Transport::send`].
+    pub fn send_message<M: DroneSend>(
+        &mut self,
+        destination: NodeId,
+        content: &M,
+    ) -> Result<u64, CodecError> {
+        let payload = self.codec.serialize(content)?;
+        self.send(destination, &payload)
+    }
+    /// Starts a new discovery round, flooding all the neighbors.
+    pub fn discover(&mut self) {
+        let flood_id = self.next_flood_id;
+        self.next_flood_id += 1;
+        let mut round = DiscoveryRound::new(
+            flood_id,
+            self.id,
+            self.node_type,
+            self.options.discovery_quiet_period,
+        );
+        let mut neighbors = self.packet_send.keys().cloned().collect::<Vec<_>>();
+        neighbors.sort();
+        for (_, packet) in round.start(neighbors, flood_id) {
+            self.forward(packet);
+        }
+        self.discovery = Some(round);
+    }
+
+    // INGESTION
+    /// Handles a packet received by the host.
+    pub fn handle_packet(&mut self, packet: Packet) -> Vec<TransportEvent> {
+        let mut events = Vec::new();
+        match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                let ack_route = Self::reply_route(&packet.routing_header);
+                self.forward(Packet::new_ack(
+                    ack_route,
+                    packet.session_id,
+                    fragment.fragment_index,
+                ));
+                let key = (
+                    packet.session_id,
+                    packet.routing_header.source().unwrap_or(self.id),
+                );
+                if self.completed.contains(&key) {
+                    // the Ack of this fragment was lost, the message was already received
+                    return events;
+                }
+                match self.codec.receive_payload(&packet) {
+                    Ok(Some(((session_id, source_id), payload))) => {
+                        self.remember_completed((session_id, source_id));
+                        events.push(TransportEvent::Received {
+                            session_id,
+                            source_id,
+                            payload,
+                        })
+                    }
+                    Ok(None) => {}
+                    Err(error) => events.push(TransportEvent::Corrupt {
+                        session_id: packet.session_id,
+                        source_id: packet.routing_header.source().unwrap_or(self.id),
+                        error,
+                    }),
+                }
+            }
+            PacketType::Ack(ack) => {
+                let Some(source) = packet.routing_header.source() else {
+                    return events;
+                };
+                let key = (packet.session_id, source);
+                let Some(outgoing) = self.outgoing.get_mut(&key) else {
+                    return events;
+                };
+                let acked = outgoing.acked.get_mut(ack.fragment_index as usize);
+                let first_ack = acked.as_ref().is_some_and(|acked| !**acked);
+                if let Some(acked) = acked {
+                    *acked = true;
+                }
+                if outgoing.acked.iter().all(|&acked| acked) {
+                    self.outgoing.remove(&key);
+                    events.push(TransportEvent::Delivered {
+                        session_id: key.0,
+                        destination: key.1,
+                    });
+                }
+                if first_ack {
+                    self.grow_window(source);
+                }
+                self.pump(source);
+            }
+            PacketType::Nack(nack) => {
+                let nacking = packet.routing_header.source();
+                match nack.nack_type {
+                    NackType::ErrorInRouting(id) => {
+                        if let Some(nacking) = nacking {
+                            self.topology.remove_edge(nacking, id);
+                        }
+                    }
+                    NackType::UnexpectedRecipient(_) | NackType::DestinationIsDrone => {
+                        self.discover_if_idle()
+                    }
+                    NackType::Dropped => {}
+                }
+                self.routes.handle_nack(&packet);
+                let index = nack.fragment_index as usize;
+                // a duplicate Nack, or one for a fragment sent again since, says nothing new
+                let Some(key) = self.nacked_message(&packet, index) else {
+                    return events;
+                };
+                let destination = key.1;
+                let outgoing = self.outgoing.get_mut(&key).expect("key taken from the map");
+                let Some(sent_at) = outgoing.sent_at[index].take() else {
+                    return events;
+                };
+                if nack.nack_type == NackType::Dropped {
+                    self.shrink_window(destination, sent_at);
+                } else {
+                    // the route is broken, the next transmission computes a new one
+                    outgoing.route = None;
+                }
+                self.pump(destination);
+            }
+            PacketType::FloodRequest(flood_request) => {
+                let mut response = flood_request
+                    .get_incremented(self.id, self.node_type)
+                    .generate_response(packet.session_id);
+                response.routing_header.increase_hop_index();
+                self.forward(response);
+            }
+            PacketType::FloodResponse(_) => {
+                if let Some(discovery) = self.discovery.as_mut() {
+                    discovery.handle_packet(&packet);
+                }
+            }
+        }
+        events
+    }
+    /// Handles a command of the controller.
+    pub fn handle_command(&mut self, command: HostCommand) {
+        match command {
+            HostCommand::AddSender(id, sender) => {
+                self.packet_send.insert(id, sender);
+                self.discover();
+            }
+            HostCommand::RemoveSender(id) => {
+                self.packet_send.remove(&id);
+                self.topology.remove_edge(self.id, id);
+                self.routes.invalidate_link(self.id, id);
+            }
+        }
+    }
+
+    // TIMEOUTS
+    /// Returns how long the host can wait for packets before calling [`Transport::tick`].
+    pub fn next_timeout(&self) -> Duration {
+        let now = Instant::now();
+        let retransmission = self
+            .outgoing
+            .values()
+            .filter(|outgoing| outgoing.route.is_some())
+            .flat_map(|outgoing| {
+                outgoing
+                    .sent_at
+                    .iter()
+                    .zip(outgoing.acked.iter())
+                    .filter(|(_, &acked)| !acked)
+                    .filter_map(|(&sent_at, _)| sent_at)
+            })
+            .map(|sent_at| {
+                (sent_at + self.options.retransmission_timeout).saturating_duration_since(now)
+            })
+            .min();
+        let discovery = self
+            .discovery
+            .as_ref()
+            .map(|discovery| discovery.remaining());
+        [retransmission, discovery]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(self.options.retransmission_timeout)
+    }
+    /// Ends the discovery round when quiet and retransmits the fragments that timed out.
+    pub fn tick(&mut self) -> Vec<TransportEvent> {
+        let mut events = Vec::new();
+        if self
+            .discovery
+            .as_ref()
+            .is_some_and(|discovery| discovery.is_finished())
+        {
+            events.extend(self.finish_discovery());
+        }
+
+        let now = Instant::now();
+        let mut keys = self.outgoing.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        let mut destinations = BTreeSet::new();
+        for key in keys {
+            let outgoing = &self.outgoing[&key];
+            let expired = (0..outgoing.fragments.len())
+                .filter(|&index| !outgoing.acked[index])
+                .filter(|&index| {
+                    outgoing.sent_at[index].is_some_and(|sent_at| {
+                        now.duration_since(sent_at) >= self.options.retransmission_timeout
+                    })
+                })
+                .collect::<Vec<_>>();
+            if expired.is_empty() {
+                continue;
+            }
+            destinations.insert(key.1);
+            let outgoing = self.outgoing.get_mut(&key).expect("key taken from the map");
+            outgoing.timeouts += 1;
+            if outgoing.timeouts > self.options.max_timeouts {
+                self.outgoing.remove(&key);
+                events.push(TransportEvent::Undeliverable {
+                    session_id: key.0,
+                    destination: key.1,
+                });
+                continue;
+            }
+            for index in expired {
+                outgoing.sent_at[index] = None;
+            }
+            self.reset_window(key.1);
+            // a packet lost without Nack usually means a drone crashed along the route
+            self.discover_if_idle();
+        }
+        for destination in destinations {
+            self.pump(destination);
+        }
+        events
+    }
+
+    // OTHERS
+    /// Sends the fragments of the message over its route, computing one if needed.
+    /// Without a route the fragments wait for the end of a discovery round.
+    fn transmit(&mut self, key: (u64, NodeId), indices: Vec<usize>) {
+        let (session_id, destination) = key;
+        let route = loop {
+            let Some(outgoing) = self.outgoing.get(&key) else {
+                return;
+            };
+            let route = match &outgoing.route {
+                Some(route) => route.clone(),
+                None => match self.routes.route(&self.topology, self.id, destination) {
+                    Some(route) => route.clone(),
+                    None => {
+                        self.discover_if_idle();
+                        return;
+                    }
+                },
+            };
+            // the neighbor may be gone since the route was computed
+            match route.current_hop() {
+                Some(first_hop) if self.packet_send.contains_key(&first_hop) => break route,
+                first_hop => {
+                    if let Some(first_hop) = first_hop {
+                        self.topology.remove_edge(self.id, first_hop);
+                        self.routes.invalidate_link(self.id, first_hop);
+                    }
+                    self.routes.invalidate(destination);
+                    if let Some(outgoing) = self.outgoing.get_mut(&key) {
+                        outgoing.route = None;
+                    }
+                }
+            }
+        };
+
+        let now = Instant::now();
+        let mut packets = Vec::new();
+        if let Some(outgoing) = self.outgoing.get_mut(&key) {
+            outgoing.route = Some(route.clone());
+            for index in indices {
+                if outgoing.acked.get(index) != Some(&false) {
+                    continue;
+                }
+                outgoing.sent_at[index] = Some(now);
+                packets.push(Packet::new_fragment(
+                    route.clone(),
+                    session_id,
+                    outgoing.fragments[index].clone(),
+                ));
+            }
+        }
+        for packet in packets {
+            self.forward(packet);
+        }
+    }
+    /// Sends the packet to its current hop, notifying the controller.
+    /// Acks, Nacks and flood responses that cannot be sent go through the controller.
+    fn forward(&mut self, packet: Packet) -> bool {
+        let sent = packet
+            .routing_header
+            .current_hop()
+            .and_then(|hop| self.packet_send.get(&hop))
+            .is_some_and(|sender| sender.send(packet.clone()).is_ok());
+        let event = match (sent, &packet.pack_type) {
+            (true, _) => HostEvent::PacketSent(packet),
+            (false, PacketType::Ack(_) | PacketType::Nack(_) | PacketType::FloodResponse(_)) => {
+                HostEvent::ControllerShortcut(packet)
+            }
+            (false, _) => return false,
+        };
+        let _ = self.controller_send.send(event);
+        sent
+    }
+    fn remember_completed(&mut self, key: SessionKey) {
+        if self.completed.insert(key) {
+            self.completed_order.push_back(key);
+        }
+        if self.completed_order.len() > COMPLETED_MEMORY {
+            if let Some(oldest) = self.completed_order.pop_front() {
+                self.completed.remove(&oldest);
+            }
+        }
+    }
+    fn discover_if_idle(&mut self) {
+        if self.discovery.is_none() {
+            self.discover();
+        }
+    }
+    /// Returns the message with the fragment in flight the Nack is about.
+    ///
+    /// Replies reuse the session id of their request, so messages to different
+    /// destinations can share it: the Nack travels back the route of the fragment up to
+    /// the drone that sent it, which tells them apart.
+    fn nacked_message(&self, packet: &Packet, index: usize) -> Option<(u64, NodeId)> {
+        let mut keys = self
+            .outgoing
+            .iter()
+            .filter(|&(&(session_id, _), outgoing)| {
+                session_id == packet.session_id
+                    && outgoing.acked.get(index) == Some(&false)
+                    && outgoing.sent_at[index].is_some()
+            })
+            .map(|(&key, _)| key)
+            .collect::<Vec<_>>();
+        if keys.len() <= 1 {
+            return keys.pop();
+        }
+        keys.sort();
+        let traveled = packet
+            .routing_header
+            .hops
+            .iter()
+            .rev()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.into_iter().find(|key| {
+            self.outgoing[key]
+                .route
+                .as_ref()
+                .is_some_and(|route| route.hops.starts_with(&traveled))
+        })
+    }
+    fn window_mut(&mut self, destination: NodeId) -> &mut Window {
+        let initial = self.options.initial_window as f64;
+        self.windows.entry(destination).or_insert_with(|| Window {
+            size: initial,
+            shrunk_at: None,
+            turn: 0,
+        })
+    }
+    /// Grows the window by one fragment over a whole window acknowledged.
+    fn grow_window(&mut self, destination: NodeId) {
+        let max = self.options.max_window as f64;
+        let window = self.window_mut(destination);
+        window.size = (window.size + 1.0 / window.size).min(max);
+    }
+    /// Halves the window, once for the fragments dropped from the same window.
+    fn shrink_window(&mut self, destination: NodeId, sent_at: Instant) {
+        let min = self.options.min_window as f64;
+        let window = self.window_mut(destination);
+        if window
+            .shrunk_at
+            .is_some_and(|shrunk_at| sent_at < shrunk_at)
+        {
+            return;
+        }
+        window.size = (window.size / 2.0).max(min);
+        window.shrunk_at = Some(Instant::now());
+    }
+    fn reset_window(&mut self, destination: NodeId) {
+        let min = self.options.min_window as f64;
+        let window = self.window_mut(destination);
+        window.size = min;
+        window.shrunk_at = Some(Instant::now());
+    }
+    /// Sends the waiting fragments to the destination that fit in its window,
+    /// one from every message in turn, each up to its share of the window.
+    fn pump(&mut self, destination: NodeId) {
+        let mut keys = self
+            .outgoing
+            .keys()
+            .filter(|(_, to)| *to == destination)
+            .cloned()
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return;
+        }
+        keys.sort();
+        let session_window = self.options.session_window.max(1);
+        let sessions = keys.len();
+        let window = self.window_mut(destination);
+        let size = (window.size as usize).max(1);
+        let turn = window.turn % sessions;
+        window.turn = window.turn.wrapping_add(1);
+        keys.rotate_left(turn);
+
+        let in_flight = keys
+            .iter()
+            .map(|key| self.outgoing[key].in_flight())
+            .collect::<Vec<_>>();
+        let mut budget = size.saturating_sub(in_flight.iter().sum());
+        let share = size.div_ceil(sessions).min(session_window);
+        // how many more fragments every message can send
+        let mut allowed = in_flight
+            .iter()
+            .map(|&in_flight| share.saturating_sub(in_flight))
+            .collect::<Vec<_>>();
+        let mut waiting = keys
+            .iter()
+            .map(|key| self.outgoing[key].waiting())
+            .collect::<Vec<_>>();
+        let mut batches = vec![Vec::new(); sessions];
+        let mut sent = true;
+        while budget > 0 && sent {
+            sent = false;
+            for ((indices, allowed), batch) in waiting
+                .iter_mut()
+                .zip(allowed.iter_mut())
+                .zip(batches.iter_mut())
+            {
+                if budget == 0 || *allowed == 0 {
+                    continue;
+                }
+                if let Some(index) = indices.pop_front() {
+                    batch.push(index);
+                    budget -= 1;
+                    *allowed -= 1;
+                    sent = true;
+                }
+            }
+        }
+        for (key, batch) in keys.into_iter().zip(batches) {
+            if !batch.is_empty() {
+                self.transmit(key, batch);
+            }
+        }
+    }
+    fn finish_discovery(&mut self) -> Vec<TransportEvent> {
+        let Some(discovery) = self.discovery.take() else {
+            return Vec::new();
+        };
+        let mut discovered = discovery.finish();
+        // links to neighbors removed during the round cannot be used anyway
+        let neighbors = discovered.neighbors(self.id);
+        for neighbor in neighbors {
+            if !self.packet_send.contains_key(&neighbor) {
+                discovered.remove_edge(self.id, neighbor);
+            }
+        }
+        let diff = self.topology.merge(&discovered, MergePolicy::Replace);
+        self.routes.update_topology(&self.topology);
+
+        let mut events = Vec::new();
+        let mut keys = self.outgoing.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        let mut destinations = BTreeSet::new();
+        for key in keys {
+            let outgoing = self.outgoing.get_mut(&key).expect("key taken from the map");
+            let stale = match &outgoing.route {
+                Some(route) => !diff.keeps_route(route),
+                None => true,
+            };
+            if !stale {
+                continue;
+            }
+            outgoing.route = None;
+            let waiting = outgoing.sent_at.iter().all(|sent_at| sent_at.is_none());
+            if self.topology.shortest_route(self.id, key.1).is_none() {
+                // the destination is not reachable even after a fresh discovery
+                if waiting {
+                    self.outgoing.remove(&key);
+                    events.push(TransportEvent::Undeliverable {
+                        session_id: key.0,
+                        destination: key.1,
+                    });
+                }
+                continue;
+            }
+            // fragments in flight on the old route are sent again on the new one
+            self.outgoing
+                .get_mut(&key)
+                .expect("key taken from the map")
+                .lose_all();
+            destinations.insert(key.1);
+        }
+        for destination in destinations {
+            self.pump(destination);
+        }
+        if !diff.is_empty() {
+            events.push(TransportEvent::TopologyChanged(diff));
+        }
+        events.push(TransportEvent::DiscoveryFinished);
+        events
+    }
+    /// Returns the route back to the source of the packet, starting from this host.
+    fn reply_route(routing_header: &SourceRoutingHeader) -> SourceRoutingHeader {
+        let mut hops = routing_header
+            .hops
+            .get(..=routing_header.hop_index)
+            .unwrap_or(&routing_header.hops)
+            .to_vec();
+        hops.reverse();
+        SourceRoutingHeader::with_first_hop(hops)
+    }
+}
@@ -0,0 +1,499 @@
+use crate::{ServerDirectory, Transport, TransportEvent};
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_controller::HostCommand;
+use wg_message::{
+    media_references, CodecError, DroneSend, MediaFile, MediaRequest, MediaResponse, ServerType,
+    ServerTypeRequest, ServerTypeResponse, TextRequest, TextResponse,
+};
+use wg_network::NodeId;
+use wg_packet::Packet;
+
+/// A request of the application to a [`WebBrowser`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum BrowserCommand {
+    /// Asks every text server for its files, answered with [`BrowserEvent::FileList`].
+    ListFiles,
+    /// Fetches a text file and the media it references, answered with [`BrowserEvent::Document`].
+    Fetch(u64),
+    /// Discovers the network again, classifying the servers not seen before.
+    Discover,
+}
+
+/// A text file with the media it references, see [`media_references`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub struct Document {
+    pub file_id: u64,
+    /// The text server the file came from.
+    pub server: NodeId,
+    pub text: String,
+    /// The referenced media found on the media servers, by id.
+    pub media: BTreeMap<u64, MediaFile>,
+    /// The referenced media no media server has, in order of appearance.
+    pub missing: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserError {
+    /// No text server was found in the network.
+    NoTextServer,
+    /// No text server has the file.
+    NotFound(u64),
+    /// A request to the server was given up.
+    Unreachable(NodeId),
+    /// The server answered `error_unsupported_request!`.
+    Unsupported(NodeId),
+    /// The server answered with a response that does not match the request.
+    UnexpectedResponse(NodeId),
+    /// The response of the server cannot be reassembled or parsed.
+    InvalidResponse { server: NodeId, error: CodecError },
+}
+
+impl Display for BrowserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrowserError::NoTextServer => write!(f, "no text server found"),
+            BrowserError::NotFound(file_id) => write!(f, "file {} not found", file_id),
+            BrowserError::Unreachable(server) => write!(f, "server {} is unreachable", server),
+            BrowserError::Unsupported(server) => {
+                write!(f, "server {} does not support the request", server)
+            }
+            BrowserError::UnexpectedResponse(server) => {
+                write!(f, "server {} sent an unexpected response", server)
+            }
+            BrowserError::InvalidResponse { server, error } => {
+                write!(f, "invalid response from server {}: {}", server, error)
+            }
+        }
+    }
+}
+
+impl Error for BrowserError {}
+
+/// Something the application should know about, sent by a [`WebBrowser`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum BrowserEvent {
+    /// A server answered `server_type?`.
+    ServerFound {
+        server: NodeId,
+        server_type: ServerType,
+    },
+    /// The files of the text servers, each with the servers that have it.
+    FileList(BTreeMap<u64, Vec<NodeId>>),
+    /// The text of a file being fetched arrived, or one of its media was found or given up.
+    Progress {
+        file_id: u64,
+        media_done: usize,
+        media_total: usize,
+    },
+    Document(Document),
+    /// A request failed. A fetch goes on with the other servers, if any.
+    Error(BrowserError),
+}
+
+/// What a request in flight asked for.
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    ServerType,
+    TextList,
+    Text(u64),
+    Media { file_id: u64, media_id: u64 },
+}
+
+/// A document being fetched.
+#[derive(Debug, Clone, Default)]
+struct Fetch {
+    /// Text servers still to ask for the file.
+    text_servers: VecDeque<NodeId>,
+    document: Option<(NodeId, String)>,
+    references: Vec<u64>,
+    /// Media servers still to ask, for every reference not yet found or given up.
+    lookups: BTreeMap<u64, VecDeque<NodeId>>,
+    media: BTreeMap<u64, MediaFile>,
+}
+
+/// A client browsing the text and media servers of the network, on its own thread.
+///
+/// After every discovery round it asks `server_type?` to the servers not seen before.
+/// The application sends [`BrowserCommand`]s, run once the servers are classified, and
+/// receives [`BrowserEvent`]s: a fetched document is looked up on the text servers that
+/// listed it first, then on the others, and each media it references on every media
+/// server in turn, until one has it.
+pub struct WebBrowser {
+    transport: Transport,
+    servers: ServerDirectory,
+    /// The files of the last listing, each with the servers that have it.
+    files: BTreeMap<u64, Vec<NodeId>>,
+    /// Requests in flight by session id, with the server they were sent to.
+    requests: HashMap<u64, (NodeId, Pending)>,
+    listing: Option<BTreeMap<u64, Vec<NodeId>>>,
+    fetches: HashMap<u64, Fetch>,
+    commands: VecDeque<BrowserCommand>,
+    controller_recv: Receiver<HostCommand>,
+    packet_recv: Receiver<Packet>,
+    command_recv: Receiver<BrowserCommand>,
+    event_send: Sender<BrowserEvent>,
+}
+
+impl WebBrowser {
+    // INITIALIZATION
+    /// Creates a browser sending through `transport`, usually of a [`wg_network::NodeType::Client`].
+    pub fn new(
+        transport: Transport,
+        controller_recv: Receiver<HostCommand>,
+        packet_recv: Receiver<Packet>,
+        command_recv: Receiver<BrowserCommand>,
+        event_send: Sender<BrowserEvent>,
+    ) -> Self {
+        Self {
+            transport,
+            servers: ServerDirectory::new(),
+            files: BTreeMap::new(),
+            requests: HashMap::new(),
+            listing: None,
+            fetches: HashMap::new(),
+            commands: VecDeque::new(),
+            controller_recv,
+            packet_recv,
+            command_recv,
+            event_send,
+        }
+    }
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+    /// Returns the servers that answered `server_type?`.
+    pub fn servers(&self) -> &ServerDirectory {
+        &self.servers
+    }
+    /// Returns the files of the last listing, each with the servers that have it.
+    pub fn files(&self) -> &BTreeMap<u64, Vec<NodeId>> {
+        &self.files
+    }
+
+    // RUN
+    /// Discovers the network and runs the commands of the application until the controller
+    /// or the application closes its channel, or the packet channel is closed.
+    pub fn run(&mut self) {
+        self.transport.discover();
+        loop {
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    let Ok(command) = command else {
+                        return;
+                    };
+                    self.transport.handle_command(command);
+                }
+                recv(self.command_recv) -> command => {
+                    let Ok(command) = command else {
+                        return;
+                    };
+                    self.commands.push_back(command);
+                }
+                recv(self.packet_recv) -> packet => {
+                    let Ok(packet) = packet else {
+                        return;
+                    };
+                    let events = self.transport.handle_packet(packet);
+                    self.handle_events(events);
+                }
+                default(self.transport.next_timeout()) => {}
+            }
+            let events = self.transport.tick();
+            self.handle_events(events);
+            self.run_commands();
+        }
+    }
+    /// Runs the commands waiting for the end of the discovery and classification of servers.
+    fn run_commands(&mut self) {
+        while self.is_ready() {
+            let Some(command) = self.commands.pop_front() else {
+                return;
+            };
+            match command {
+                BrowserCommand::ListFiles => self.list_files(),
+                BrowserCommand::Fetch(file_id) => self.fetch(file_id),
+                BrowserCommand::Discover => self.transport.discover(),
+            }
+        }
+    }
+    fn is_ready(&self) -> bool {
+        !self.transport.is_discovering() && !self.servers.is_classifying()
+    }
+    fn handle_events(&mut self, events: Vec<TransportEvent>) {
+        for event in events {
+            match event {
+                TransportEvent::Received {
+                    session_id,
+                    source_id,
+                    payload,
+                } => {
+                    if let Some(pending) = self.take_request(session_id, source_id) {
+                        self.handle_response(source_id, pending, &payload);
+                    }
+                }
+                TransportEvent::Corrupt {
+                    session_id,
+                    source_id,
+                    error,
+                } => {
+                    if let Some(pending) = self.take_request(session_id, source_id) {
+                        let error = BrowserError::InvalidResponse {
+                            server: source_id,
+                            error,
+                        };
+                        self.fail(source_id, pending, Some(error));
+                    }
+                }
+                TransportEvent::Undeliverable {
+                    session_id,
+                    destination,
+                } => {
+                    if let Some(pending) = self.take_request(session_id, destination) {
+                        self.fail(
+                            destination,
+                            pending,
+                            Some(BrowserError::Unreachable(destination)),
+                        );
+                    }
+                }
+                TransportEvent::TopologyChanged(_) => self.classify_servers(),
+                TransportEvent::Delivered { .. } | TransportEvent::DiscoveryFinished => {}
+            }
+        }
+    }
+
+    // REQUESTS
+    fn send(&mut self, server: NodeId, pending: Pending, request: &impl DroneSend) {
+        // requests are far below the size limit of the codec
+        if let Ok(session_id) = self.transport.send_message(server, request) {
+            self.requests.insert(session_id, (server, pending));
+        }
+    }
+    fn take_request(&mut self, session_id: u64, server: NodeId) -> Option<Pending> {
+        match self.requests.get(&session_id) {
+            Some(&(sent_to, pending)) if sent_to == server => {
+                self.requests.remove(&session_id);
+                Some(pending)
+            }
+            _ => None,
+        }
+    }
+    /// Asks `server_type?` to the servers not classified nor being classified.
+    fn classify_servers(&mut self) {
+        for server in self.servers.to_classify(self.transport.topology()) {
+            self.send(server, Pending::ServerType, &ServerTypeRequest);
+        }
+    }
+    fn list_files(&mut self) {
+        if self.listing.is_some() {
+            return;
+        }
+        let text_servers = self.servers.servers_of(&ServerType::Text);
+        if text_servers.is_empty() {
+            self.emit(BrowserEvent::Error(BrowserError::NoTextServer));
+            return;
+        }
+        self.listing = Some(BTreeMap::new());
+        for server in text_servers {
+            self.send(server, Pending::TextList, &TextRequest::TextList);
+        }
+    }
+    fn fetch(&mut self, file_id: u64) {
+        if self.fetches.contains_key(&file_id) {
+            return;
+        }
+        let mut text_servers = self.files.get(&file_id).cloned().unwrap_or_default();
+        for server in self.servers.servers_of(&ServerType::Text) {
+            if !text_servers.contains(&server) {
+                text_servers.push(server);
+            }
+        }
+        if text_servers.is_empty() {
+            self.emit(BrowserEvent::Error(BrowserError::NoTextServer));
+            return;
+        }
+        let fetch = Fetch {
+            text_servers: text_servers.into(),
+            ..Fetch::default()
+        };
+        self.fetches.insert(file_id, fetch);
+        self.request_text(file_id);
+    }
+    /// Asks the file to the next text server, giving up if none is left.
+    fn request_text(&mut self, file_id: u64) {
+        let Some(fetch) = self.fetches.get_mut(&file_id) else {
+            return;
+        };
+        match fetch.text_servers.pop_front() {
+            Some(server) => self.send(server, Pending::Text(file_id), &TextRequest::Text(file_id)),
+            None => {
+                self.fetches.remove(&file_id);
+                self.emit(BrowserEvent::Error(BrowserError::NotFound(file_id)));
+            }
+        }
+    }
+    /// Asks the media to the next media server, giving up if none is left.
+    fn request_media(&mut self, file_id: u64, media_id: u64) {
+        let Some(fetch) = self.fetches.get_mut(&file_id) else {
+            return;
+        };
+        let Some(media_servers) = fetch.lookups.get_mut(&media_id) else {
+            return;
+        };
+        match media_servers.pop_front() {
+            Some(server) => self.send(
+                server,
+                Pending::Media { file_id, media_id },
+                &MediaRequest::Media(media_id),
+            ),
+            None => {
+                fetch.lookups.remove(&media_id);
+                self.progress(file_id);
+            }
+        }
+    }
+
+    // RESPONSES
+    fn handle_response(&mut self, server: NodeId, pending: Pending, payload: &[u8]) {
+        let codec = self.transport.codec();
+        let error = match pending {
+            Pending::ServerType => match codec.decode(payload) {
+                Ok(ServerTypeResponse(server_type)) => {
+                    self.servers.insert(server, server_type.clone());
+                    return self.emit(BrowserEvent::ServerFound {
+                        server,
+                        server_type,
+                    });
+                }
+                Err(error) => BrowserError::InvalidResponse { server, error },
+            },
+            Pending::TextList => match codec.decode(payload) {
+                Ok(TextResponse::TextList(file_ids)) => {
+                    if let Some(listing) = self.listing.as_mut() {
+                        for file_id in file_ids {
+                            listing.entry(file_id).or_default().push(server);
+                        }
+                    }
+                    return self.finish_listing();
+                }
+                response => Self::text_error(server, response),
+            },
+            Pending::Text(file_id) => match codec.decode(payload) {
+                Ok(TextResponse::Text(text)) => return self.text_received(file_id, server, text),
+                // not an error, another server may have the file
+                Ok(TextResponse::NotFound) => return self.request_text(file_id),
+                response => Self::text_error(server, response),
+            },
+            Pending::Media { file_id, media_id } => match codec.decode(payload) {
+                Ok(MediaResponse::Media(media)) => {
+                    return self.media_received(file_id, media_id, media)
+                }
+                Ok(MediaResponse::NotFound) => return self.request_media(file_id, media_id),
+                Ok(MediaResponse::UnsupportedRequest) => BrowserError::Unsupported(server),
+                Ok(_) => BrowserError::UnexpectedResponse(server),
+                Err(error) => BrowserError::InvalidResponse { server, error },
+            },
+        };
+        self.fail(server, pending, Some(error));
+    }
+    fn text_error(server: NodeId, response: Result<TextResponse, CodecError>) -> BrowserError {
+        match response {
+            Ok(TextResponse::UnsupportedRequest) => BrowserError::Unsupported(server),
+            Ok(_) => BrowserError::UnexpectedResponse(server),
+            Err(error) => BrowserError::InvalidResponse { server, error },
+        }
+    }
+    /// Reports the error and moves the operation of the failed request on.
+    fn fail(&mut self, server: NodeId, pending: Pending, error: Option<BrowserError>) {
+        if let Some(error) = error {
+            self.emit(BrowserEvent::Error(error));
+        }
+        match pending {
+            // asked again after the next discovery round that changes the topology
+            Pending::ServerType => self.servers.failed(server),
+            Pending::TextList => self.finish_listing(),
+            Pending::Text(file_id) => self.request_text(file_id),
+            Pending::Media { file_id, media_id } => self.request_media(file_id, media_id),
+        }
+    }
+    fn finish_listing(&mut self) {
+        let waiting = self
+            .requests
+            .values()
+            .any(|(_, pending)| matches!(pending, Pending::TextList));
+        if waiting {
+            return;
+        }
+        if let Some(files) = self.listing.take() {
+            self.files = files.clone();
+            self.emit(BrowserEvent::FileList(files));
+        }
+    }
+    fn text_received(&mut self, file_id: u64, server: NodeId, text: String) {
+        let media_servers = self.servers.servers_of(&ServerType::Media);
+        let Some(fetch) = self.fetches.get_mut(&file_id) else {
+            return;
+        };
+        let references = media_references(&text);
+        fetch.document = Some((server, text));
+        fetch.lookups = references
+            .iter()
+            .map(|&media_id| (media_id, media_servers.iter().cloned().collect()))
+            .collect();
+        fetch.references = references.clone();
+        self.progress(file_id);
+        for media_id in references {
+            self.request_media(file_id, media_id);
+        }
+    }
+    fn media_received(&mut self, file_id: u64, media_id: u64, media: MediaFile) {
+        let Some(fetch) = self.fetches.get_mut(&file_id) else {
+            return;
+        };
+        fetch.lookups.remove(&media_id);
+        fetch.media.insert(media_id, media);
+        self.progress(file_id);
+    }
+    /// Reports the progress of the fetch, sending the document once all its media are resolved.
+    fn progress(&mut self, file_id: u64) {
+        let Some(fetch) = self.fetches.get(&file_id) else {
+            return;
+        };
+        let media_total = fetch.references.len();
+        self.emit(BrowserEvent::Progress {
+            file_id,
+            media_done: media_total - fetch.lookups.len(),
+            media_total,
+        });
+        if !fetch.lookups.is_empty() {
+            return;
+        }
+        let Some(fetch) = self.fetches.remove(&file_id) else {
+            return;
+        };
+        let Some((server, text)) = fetch.document else {
+            return;
+        };
+        let missing = fetch
+            .references
+            .into_iter()
+            .filter(|media_id| !fetch.media.contains_key(media_id))
+            .collect();
+        self.emit(BrowserEvent::Document(Document {
+            file_id,
+            server,
+            text,
+            media: fetch.media,
+            missing,
+        }));
+    }
+    fn emit(&self, event: BrowserEvent) {
+        let _ = self.event_send.send(event);
+    }
+}
@@ -1,6 +1,8 @@
 pub use wg_config as config;
 pub use wg_controller as controller;
 pub use wg_drone as drone;
+pub use wg_host as host;
+pub use wg_message as message;
 pub use wg_network as network;
 pub use wg_packet as packet;
 pub use wg_tests as tests;
@@ -0,0 +1,41 @@
+use crate::{DroneSend, Request, Response};
+use serde::{Deserialize, Serialize};
+use wg_network::NodeId;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum ChatRequest {
+    /// `client_list?`
+    ClientList,
+    /// `registration_to_chat`
+    Register(NodeId),
+    /// `message_for?(client_id, message)`
+    SendMessage {
+        from: NodeId,
+        to: NodeId,
+        message: String,
+    },
+}
+
+impl DroneSend for ChatRequest {}
+impl Request for ChatRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum ChatResponse {
+    /// `client_list!(list_of_client_ids)`
+    ClientList(Vec<NodeId>),
+    /// `message_from!(client_id, message)`
+    MessageFrom {
+        from: NodeId,
+        message: Vec<u8>,
+    },
+    MessageSent,
+    /// `error_wrong_client_id!`
+    WrongClientId,
+    /// `error_unsupported_request!`
+    UnsupportedRequest,
+}
+
+impl DroneSend for ChatResponse {}
+impl Response for ChatResponse {}
@@ -0,0 +1,526 @@
+use crate::{Compression, CompressionStats, DroneSend, Message, MessageEncoding};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_network::{NodeId, SourceRoutingHeader};
+use wg_packet::{Fragment, Packet, PacketType, FRAGMENT_DSIZE};
+
+/// Default limit on the size of a serialized message: 16 MiB.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// How many messages can be partially received at once, the least recently updated
+/// are discarded beyond.
+pub const MAX_PARTIAL_MESSAGES: usize = 1024;
+
+/// Identifies a message in transit: `(session_id, source_id)`.
+pub type SessionKey = (u64, NodeId);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// The serialized message is bigger than the limit of the codec.
+    TooLarge { size: usize, max_size: usize },
+    /// The packet does not carry a fragment.
+    NotAFragment,
+    /// The fragment is malformed: out of range index, no fragments or too long data.
+    InvalidFragment {
+        fragment_index: u64,
+        total_n_fragments: u64,
+        length: u8,
+    },
+    /// A fragment disagrees with the previous ones of the same session on the total.
+    InconsistentTotal { expected: u64, found: u64 },
+    /// The packet has no source to reply to.
+    MissingSource,
+    /// The reassembled payload is empty, without even the tag of its encoding.
+    EmptyPayload,
+    /// The payload starts with a tag that is not of a [`MessageEncoding`].
+    UnknownEncoding(u8),
+    /// The reassembled JSON payload is not valid UTF-8.
+    InvalidUtf8,
+    /// The reassembled payload cannot be parsed as the expected content.
+    Unparsable(String),
+    /// The content cannot be serialized in the encoding.
+    Unserializable(String),
+    /// The payload is flagged as compressed but cannot be decompressed.
+    InvalidCompression(String),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::TooLarge { size, max_size } => write!(
+                f,
+                "message of {} bytes is over the limit of {} bytes",
+                size, max_size
+            ),
+            CodecError::NotAFragment => write!(f, "packet is not a fragment"),
+            CodecError::InvalidFragment {
+                fragment_index,
+                total_n_fragments,
+                length,
+            } => write!(
+                f,
+                "invalid fragment {} of {} with length {}",
+                fragment_index, total_n_fragments, length
+            ),
+            CodecError::InconsistentTotal { expected, found } => write!(
+                f,
+                "fragment says the message has {} fragments instead of {}",
+                found, expected
+            ),
+            CodecError::MissingSource => write!(f, "packet has no source"),
+            CodecError::EmptyPayload => write!(f, "payload is empty"),
+            CodecError::UnknownEncoding(tag) => write!(f, "unknown encoding tag {:#04x}", tag),
+            CodecError::InvalidUtf8 => write!(f, "payload is not valid UTF-8"),
+            CodecError::Unparsable(error) => write!(f, "cannot parse payload: {}", error),
+            CodecError::Unserializable(error) => write!(f, "cannot serialize content: {}", error),
+            CodecError::InvalidCompression(error) => {
+                write!(f, "cannot decompress payload: {}", error)
+            }
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+/// The fragments of a message received so far.
+///
+/// Only the data received is stored, as `total_n_fragments` comes from the sender.
+#[derive(Debug, Clone)]
+struct PartialMessage {
+    total_n_fragments: u64,
+    fragments: BTreeMap<u64, Vec<u8>>,
+    /// Bytes of data received.
+    size: usize,
+    /// When a fragment last arrived, in fragments received by the codec.
+    updated: u64,
+}
+
+impl PartialMessage {
+    fn new(total_n_fragments: u64) -> Self {
+        Self {
+            total_n_fragments,
+            fragments: BTreeMap::new(),
+            size: 0,
+            updated: 0,
+        }
+    }
+    /// Stores the fragment, ignoring duplicates. Returns the bytes added.
+    fn insert(&mut self, fragment: &Fragment) -> usize {
+        if self.fragments.contains_key(&fragment.fragment_index) {
+            return 0;
+        }
+        let data = fragment.data[..fragment.length as usize].to_vec();
+        let length = data.len();
+        self.fragments.insert(fragment.fragment_index, data);
+        self.size += length;
+        length
+    }
+    fn is_complete(&self) -> bool {
+        self.fragments.len() as u64 == self.total_n_fragments
+    }
+    /// Returns the payload, joining the data of the fragments in order.
+    fn into_payload(self) -> Vec<u8> {
+        self.fragments.into_values().flatten().collect()
+    }
+}
+
+/// Turns high-level messages into fragments and back.
+///
+/// A message is serialized in the [`MessageEncoding`] of the codec and split in fragments
+/// of [`FRAGMENT_DSIZE`] bytes, the last one possibly shorter, after being compressed
+/// if that makes it smaller, see [`Compression`]. Received messages are decoded in the
+/// encoding and compression they were sent with.
+/// On the receiving side fragments are collected per `(session_id, source_id)`,
+/// in any order and ignoring duplicates, until the message is complete.
+/// At most [`MAX_PARTIAL_MESSAGES`] messages, and as many bytes as fit in a few messages
+/// of the maximum size, are kept: beyond, the least recently updated are discarded.
+#[derive(Debug, Clone)]
+pub struct MessageCodec {
+    max_size: usize,
+    encoding: MessageEncoding,
+    compression: Compression,
+    stats: CompressionStats,
+    partial: HashMap<SessionKey, PartialMessage>,
+    /// Bytes of data held by the partial messages.
+    buffered: usize,
+    /// Fragments received, to order the partial messages by their last update.
+    clock: u64,
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+impl MessageCodec {
+    // INITIALIZATION
+    /// Creates a codec refusing messages bigger than `max_size` bytes, in both directions.
+    pub fn new(max_size: usize) -> Self {
+        Self::with_encoding(max_size, MessageEncoding::default())
+    }
+    pub fn with_encoding(max_size: usize, encoding: MessageEncoding) -> Self {
+        Self {
+            max_size,
+            encoding,
+            compression: Compression::default(),
+            stats: CompressionStats::default(),
+            partial: HashMap::new(),
+            buffered: 0,
+            clock: 0,
+        }
+    }
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+    /// Returns the encoding of the messages sent.
+    pub fn encoding(&self) -> MessageEncoding {
+        self.encoding
+    }
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+    /// Returns how much compression saved on the messages serialized so far.
+    pub fn stats(&self) -> CompressionStats {
+        self.stats
+    }
+
+    // SENDING
+    /// Serializes the content in the encoding of the codec, tag included,
+    /// and compresses it if that makes it smaller.
+    /// Fails if the payload is bigger than the limit of the codec, as it could not be sent.
+    pub fn serialize<M: DroneSend>(&mut self, content: &M) -> Result<Vec<u8>, CodecError> {
+        self.serialize_with(content, self.compression)
+    }
+    /// Serializes the content as [`MessageCodec::serialize`], with another compression,
+    /// as for content already compressed.
+    pub fn serialize_with<M: DroneSend>(
+        &mut self,
+        content: &M,
+        compression: Compression,
+    ) -> Result<Vec<u8>, CodecError> {
+        let payload = self.encoding.encode(content)?;
+        let original = payload.len();
+        let payload = compression.compress(&payload).unwrap_or(payload);
+        if payload.len() > self.max_size {
+            return Err(CodecError::TooLarge {
+                size: payload.len(),
+                max_size: self.max_size,
+            });
+        }
+        self.stats.record(original, payload.len());
+        Ok(payload)
+    }
+    /// Serializes the content and splits it in fragments.
+    pub fn fragment<M: DroneSend>(&mut self, content: &M) -> Result<Vec<Fragment>, CodecError> {
+        let payload = self.serialize(content)?;
+        self.fragment_payload(&payload)
+    }
+    /// Splits an already serialized payload in fragments.
+    pub fn fragment_payload(&self, payload: &[u8]) -> Result<Vec<Fragment>, CodecError> {
+        if payload.len() > self.max_size {
+            return Err(CodecError::TooLarge {
+                size: payload.len(),
+                max_size: self.max_size,
+            });
+        }
+        let total_n_fragments = payload.len().div_ceil(FRAGMENT_DSIZE).max(1) as u64;
+        let mut fragments = payload
+            .chunks(FRAGMENT_DSIZE)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut data = [0; FRAGMENT_DSIZE];
+                data[..chunk.len()].copy_from_slice(chunk);
+                Fragment {
+                    fragment_index: index as u64,
+                    total_n_fragments,
+                    length: chunk.len() as u8,
+                    data,
+                }
+            })
+            .collect::<Vec<_>>();
+        if fragments.is_empty() {
+            fragments.push(Fragment {
+                fragment_index: 0,
+                total_n_fragments,
+                length: 0,
+                data: [0; FRAGMENT_DSIZE],
+            });
+        }
+        Ok(fragments)
+    }
+    /// Returns the packets carrying the message over the route, with the session id of the message.
+    /// The route should start at `message.source_id`.
+    pub fn encode<M: DroneSend>(
+        &mut self,
+        message: &Message<M>,
+        route: &SourceRoutingHeader,
+    ) -> Result<Vec<Packet>, CodecError> {
+        Ok(self
+            .fragment(&message.content)?
+            .into_iter()
+            .map(|fragment| Packet::new_fragment(route.clone(), message.session_id, fragment))
+            .collect())
+    }
+
+    // RECEIVING
+    /// Collects the fragment in the packet, returning the payload once all the fragments
+    /// of its message arrived, keyed by `(session_id, source_id)`.
+    ///
+    /// A malformed fragment discards what was received for its message.
+    pub fn receive_payload(
+        &mut self,
+        packet: &Packet,
+    ) -> Result<Option<(SessionKey, Vec<u8>)>, CodecError> {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return Err(CodecError::NotAFragment);
+        };
+        let source = packet
+            .routing_header
+            .source()
+            .ok_or(CodecError::MissingSource)?;
+        let key = (packet.session_id, source);
+        let result = self.insert(key, fragment);
+        if result.is_err() {
+            self.remove_partial(key);
+        }
+        result
+    }
+    /// Collects the fragment in the packet, returning the message once all its fragments arrived.
+    pub fn receive<M: DroneSend>(
+        &mut self,
+        packet: &Packet,
+    ) -> Result<Option<Message<M>>, CodecError> {
+        let Some(((session_id, source_id), payload)) = self.receive_payload(packet)? else {
+            return Ok(None);
+        };
+        Ok(Some(Message {
+            source_id,
+            session_id,
+            content: self.decode(&payload)?,
+        }))
+    }
+    /// Deserializes a payload in the encoding of its tag, see [`MessageEncoding::decode`],
+    /// decompressing it first if flagged.
+    pub fn decode<M: DroneSend>(&self, payload: &[u8]) -> Result<M, CodecError> {
+        if Compression::is_compressed(payload) {
+            return MessageEncoding::decode(&Compression::decompress(payload, self.max_size)?);
+        }
+        MessageEncoding::decode(payload)
+    }
+    /// Returns the number of messages partially received.
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+    /// Discards what was received of a message.
+    pub fn discard(&mut self, session_id: u64, source_id: NodeId) -> bool {
+        self.remove_partial((session_id, source_id)).is_some()
+    }
+
+    // OTHERS
+    fn insert(
+        &mut self,
+        key: SessionKey,
+        fragment: &Fragment,
+    ) -> Result<Option<(SessionKey, Vec<u8>)>, CodecError> {
+        let total_n_fragments = fragment.total_n_fragments;
+        if total_n_fragments == 0
+            || fragment.fragment_index >= total_n_fragments
+            || fragment.length as usize > FRAGMENT_DSIZE
+        {
+            return Err(CodecError::InvalidFragment {
+                fragment_index: fragment.fragment_index,
+                total_n_fragments,
+                length: fragment.length,
+            });
+        }
+        let max_fragments = self.max_size.div_ceil(FRAGMENT_DSIZE).max(1) as u64;
+        if total_n_fragments > max_fragments {
+            return Err(CodecError::TooLarge {
+                size: (total_n_fragments as usize).saturating_mul(FRAGMENT_DSIZE),
+                max_size: self.max_size,
+            });
+        }
+        if let Some(partial) = self.partial.get(&key) {
+            if partial.total_n_fragments != total_n_fragments {
+                return Err(CodecError::InconsistentTotal {
+                    expected: partial.total_n_fragments,
+                    found: total_n_fragments,
+                });
+            }
+        }
+        self.make_room(key, fragment.length as usize);
+        self.clock += 1;
+        let partial = self
+            .partial
+            .entry(key)
+            .or_insert_with(|| PartialMessage::new(total_n_fragments));
+        partial.updated = self.clock;
+        self.buffered += partial.insert(fragment);
+        if !partial.is_complete() {
+            return Ok(None);
+        }
+        let Some(payload) = self.remove_partial(key).map(PartialMessage::into_payload) else {
+            return Ok(None);
+        };
+        if payload.len() > self.max_size {
+            return Err(CodecError::TooLarge {
+                size: payload.len(),
+                max_size: self.max_size,
+            });
+        }
+        Ok(Some((key, payload)))
+    }
+    /// Discards the least recently updated messages other than `key` until `length` more
+    /// bytes fit, and a new message too if `key` is not being received yet.
+    fn make_room(&mut self, key: SessionKey, length: usize) {
+        let max_buffered = self.max_size.saturating_mul(4);
+        loop {
+            let full = self.buffered + length > max_buffered
+                || (!self.partial.contains_key(&key) && self.partial.len() >= MAX_PARTIAL_MESSAGES);
+            if !full {
+                return;
+            }
+            let stale = self
+                .partial
+                .iter()
+                .filter(|(&other, _)| other != key)
+                .min_by_key(|(_, partial)| partial.updated)
+                .map(|(&other, _)| other);
+            let Some(stale) = stale else {
+                return;
+            };
+            self.remove_partial(stale);
+        }
+    }
+    fn remove_partial(&mut self, key: SessionKey) -> Option<PartialMessage> {
+        let partial = self.partial.remove(&key)?;
+        self.buffered -= partial.size;
+        Some(partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextResponse;
+
+    fn packet(session_id: u64, source_id: NodeId, fragment: Fragment) -> Packet {
+        Packet::new_fragment(
+            SourceRoutingHeader::new(vec![source_id, 100], 1),
+            session_id,
+            fragment,
+        )
+    }
+
+    fn fragment(fragment_index: u64, total_n_fragments: u64) -> Fragment {
+        Fragment {
+            fragment_index,
+            total_n_fragments,
+            length: FRAGMENT_DSIZE as u8,
+            data: [fragment_index as u8; FRAGMENT_DSIZE],
+        }
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn round_trip_over_many_fragments() {
+        let mut codec = MessageCodec::default();
+        let text = "a".repeat(FRAGMENT_DSIZE * 3 + 10);
+        let message = Message {
+            source_id: 1,
+            session_id: 7,
+            content: TextResponse::Text(text.clone()),
+        };
+        let route = SourceRoutingHeader::new(vec![1, 2, 3], 1);
+        let packets = codec.encode(&message, &route).unwrap();
+        assert!(packets.len() > 1);
+
+        let mut received = None;
+        for packet in &packets {
+            assert!(received.is_none());
+            received = codec.receive::<TextResponse>(packet).unwrap();
+        }
+        let received = received.unwrap();
+        assert_eq!((received.session_id, received.source_id), (7, 1));
+        assert!(matches!(received.content, TextResponse::Text(t) if t == text));
+        assert_eq!(codec.pending(), 0);
+    }
+
+    #[test]
+    fn out_of_order_and_duplicate_fragments() {
+        let mut codec = MessageCodec::default();
+        let sent = payload(FRAGMENT_DSIZE * 3 + 5);
+        let fragments = codec.fragment_payload(&sent).unwrap();
+        assert_eq!(fragments.len(), 4);
+
+        for index in [3, 1, 1, 0, 3] {
+            let result = codec.receive_payload(&packet(1, 1, fragments[index].clone()));
+            assert_eq!(result, Ok(None));
+        }
+        assert_eq!(codec.pending(), 1);
+        let result = codec.receive_payload(&packet(1, 1, fragments[2].clone()));
+        assert_eq!(result, Ok(Some(((1, 1), sent))));
+        assert_eq!(codec.pending(), 0);
+    }
+
+    #[test]
+    fn inconsistent_total_discards_the_message() {
+        let mut codec = MessageCodec::default();
+        assert_eq!(
+            codec.receive_payload(&packet(1, 1, fragment(0, 3))),
+            Ok(None)
+        );
+        assert_eq!(
+            codec.receive_payload(&packet(1, 1, fragment(1, 4))),
+            Err(CodecError::InconsistentTotal {
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(codec.pending(), 0);
+    }
+
+    #[test]
+    fn oversize_messages_are_rejected() {
+        let mut codec = MessageCodec::new(FRAGMENT_DSIZE * 2);
+        assert!(matches!(
+            codec.fragment_payload(&payload(FRAGMENT_DSIZE * 2 + 1)),
+            Err(CodecError::TooLarge { size, max_size })
+                if size == FRAGMENT_DSIZE * 2 + 1 && max_size == FRAGMENT_DSIZE * 2
+        ));
+        assert!(matches!(
+            codec.receive_payload(&packet(1, 1, fragment(0, 3))),
+            Err(CodecError::TooLarge { .. })
+        ));
+        assert_eq!(codec.pending(), 0);
+    }
+
+    #[test]
+    fn least_recently_updated_message_is_evicted() {
+        let mut codec = MessageCodec::default();
+        for session_id in 0..MAX_PARTIAL_MESSAGES as u64 {
+            let result = codec.receive_payload(&packet(session_id, 1, fragment(0, 3)));
+            assert_eq!(result, Ok(None));
+        }
+        // session 0 becomes the most recently updated, leaving session 1 the least
+        assert_eq!(
+            codec.receive_payload(&packet(0, 1, fragment(1, 3))),
+            Ok(None)
+        );
+        let result = codec.receive_payload(&packet(MAX_PARTIAL_MESSAGES as u64, 1, fragment(0, 3)));
+        assert_eq!(result, Ok(None));
+
+        assert_eq!(codec.pending(), MAX_PARTIAL_MESSAGES);
+        assert!(!codec.discard(1, 1));
+        assert!(codec.discard(0, 1));
+        assert!(codec.discard(2, 1));
+    }
+}
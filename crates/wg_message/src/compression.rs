@@ -0,0 +1,98 @@
+use crate::CodecError;
+use miniz_oxide::inflate::TINFLStatus;
+use wg_packet::FRAGMENT_DSIZE;
+
+/// Bit of the tag of a payload set when the content after the tag is compressed.
+pub const COMPRESSED_FLAG: u8 = 0x80;
+
+/// Compression of the payloads before fragmentation.
+///
+/// Whether a payload is compressed is told by [`COMPRESSED_FLAG`] in its tag, so
+/// receivers decompress it whatever their own setting. A payload is sent as it is when
+/// compression does not make it smaller, as for media already compressed.
+/// It is off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Deflate with the given level, from 1, the fastest, to 9, the smallest.
+    Deflate(u8),
+}
+
+impl Compression {
+    /// Compresses the content of the payload, after its tag, flagging it.
+    /// Returns None if compression is off or does not shrink the payload.
+    pub fn compress(self, payload: &[u8]) -> Option<Vec<u8>> {
+        let Compression::Deflate(level) = self else {
+            return None;
+        };
+        let (&tag, content) = payload.split_first()?;
+        let mut compressed = vec![tag | COMPRESSED_FLAG];
+        compressed.extend(miniz_oxide::deflate::compress_to_vec(content, level));
+        (compressed.len() < payload.len()).then_some(compressed)
+    }
+    /// Returns true if the content of the payload is compressed.
+    pub fn is_compressed(payload: &[u8]) -> bool {
+        payload
+            .first()
+            .is_some_and(|tag| tag & COMPRESSED_FLAG != 0)
+    }
+    /// Decompresses the content of a flagged payload, refusing to inflate it over `max_size`
+    /// bytes. The payload returned is not flagged anymore.
+    pub fn decompress(payload: &[u8], max_size: usize) -> Result<Vec<u8>, CodecError> {
+        let (&tag, content) = payload.split_first().ok_or(CodecError::EmptyPayload)?;
+        let mut decompressed = vec![tag & !COMPRESSED_FLAG];
+        match miniz_oxide::inflate::decompress_to_vec_with_limit(content, max_size) {
+            Ok(content) => decompressed.extend(content),
+            Err(error) if error.status == TINFLStatus::HasMoreOutput => {
+                return Err(CodecError::TooLarge {
+                    size: max_size.saturating_add(1),
+                    max_size,
+                })
+            }
+            Err(error) => return Err(CodecError::InvalidCompression(error.to_string())),
+        }
+        Ok(decompressed)
+    }
+}
+
+/// How much compression saved on the messages serialized by a codec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub messages: u64,
+    /// Messages sent compressed, the others did not get smaller.
+    pub compressed_messages: u64,
+    /// Size of the payloads without compression.
+    pub original_bytes: u64,
+    /// Size of the payloads as sent.
+    pub sent_bytes: u64,
+    pub original_fragments: u64,
+    pub sent_fragments: u64,
+}
+
+impl CompressionStats {
+    pub fn bytes_saved(&self) -> u64 {
+        self.original_bytes - self.sent_bytes
+    }
+    pub fn fragments_saved(&self) -> u64 {
+        self.original_fragments - self.sent_fragments
+    }
+    /// Returns the size of the payloads as sent over their size without compression.
+    pub fn ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 1.0;
+        }
+        self.sent_bytes as f64 / self.original_bytes as f64
+    }
+    pub(crate) fn record(&mut self, original: usize, sent: usize) {
+        let fragments = |size: usize| size.div_ceil(FRAGMENT_DSIZE).max(1) as u64;
+        self.messages += 1;
+        if sent < original {
+            self.compressed_messages += 1;
+        }
+        self.original_bytes += original as u64;
+        self.sent_bytes += sent as u64;
+        self.original_fragments += fragments(original);
+        self.sent_fragments += fragments(sent);
+    }
+}
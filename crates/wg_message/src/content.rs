@@ -0,0 +1,30 @@
+/// Returns the id of a file served by a text or media server, from its name.
+///
+/// Ids are a hash of the name (64 bit FNV-1a), so they are stable across restarts and
+/// servers, and a document can reference media by name, see [`media_references`].
+pub fn file_id(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Returns the ids of the media referenced by a text document, in order of first appearance.
+///
+/// A reference is `media:` followed by the name of the media file, up to a space,
+/// a quote or a closing bracket, as in `![a cat](media:cat.png)`.
+/// Punctuation at the end of the name is ignored, so references can end a sentence.
+pub fn media_references(text: &str) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for (start, _) in text.match_indices("media:") {
+        let name = text[start + "media:".len()..]
+            .split(|c: char| c.is_whitespace() || matches!(c, ')' | ']' | '"' | '\'' | '>'))
+            .next()
+            .unwrap_or("")
+            .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        let id = file_id(name);
+        if !name.is_empty() && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
@@ -0,0 +1,85 @@
+use crate::CodecError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// How the content of a message is turned into bytes.
+///
+/// Every payload starts with the tag of its encoding, so it is in the first fragment
+/// and receivers decode messages in any encoding, whatever the one they send with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MessageEncoding {
+    /// `serde_json`, readable when debugging, but a `Vec<u8>` becomes an array of
+    /// numbers, up to 4 bytes for every byte.
+    Json,
+    /// `postcard`: variable length integers and byte arrays as they are.
+    #[default]
+    Binary,
+}
+
+impl Display for MessageEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageEncoding::Json => write!(f, "json"),
+            MessageEncoding::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl MessageEncoding {
+    pub const ALL: [MessageEncoding; 2] = [MessageEncoding::Json, MessageEncoding::Binary];
+
+    pub fn tag(self) -> u8 {
+        match self {
+            MessageEncoding::Json => 0x01,
+            MessageEncoding::Binary => 0x02,
+        }
+    }
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|encoding| encoding.tag() == tag)
+    }
+    /// Returns the encoding of a payload from its tag.
+    pub fn detect(payload: &[u8]) -> Result<Self, CodecError> {
+        let &tag = payload.first().ok_or(CodecError::EmptyPayload)?;
+        Self::from_tag(tag).ok_or(CodecError::UnknownEncoding(tag))
+    }
+
+    /// Serializes the content, after the tag of the encoding.
+    pub fn encode<M: Serialize>(self, content: &M) -> Result<Vec<u8>, CodecError> {
+        let tagged = vec![self.tag()];
+        let payload = match self {
+            MessageEncoding::Json => {
+                let mut payload = tagged;
+                serde_json::to_writer(&mut payload, content)
+                    .map(|_| payload)
+                    .map_err(|error| error.to_string())
+            }
+            MessageEncoding::Binary => {
+                postcard::to_extend(content, tagged).map_err(|error| error.to_string())
+            }
+        };
+        payload.map_err(CodecError::Unserializable)
+    }
+    /// Deserializes a payload in any encoding, detected from its tag.
+    pub fn decode<M: DeserializeOwned>(payload: &[u8]) -> Result<M, CodecError> {
+        let data = payload.get(1..).unwrap_or_default();
+        match Self::detect(payload)? {
+            MessageEncoding::Json => {
+                let raw = std::str::from_utf8(data).map_err(|_| CodecError::InvalidUtf8)?;
+                serde_json::from_str(raw).map_err(|error| CodecError::Unparsable(error.to_string()))
+            }
+            MessageEncoding::Binary => {
+                let (content, rest) = postcard::take_from_bytes(data)
+                    .map_err(|error| CodecError::Unparsable(error.to_string()))?;
+                // a prefix of the payload can be valid content of another type
+                if !rest.is_empty() {
+                    return Err(CodecError::Unparsable(format!(
+                        "{} bytes after the content",
+                        rest.len()
+                    )));
+                }
+                Ok(content)
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+mod chat;
+mod codec;
+mod compression;
+mod content;
+mod encoding;
+mod media;
+mod message;
+mod server_type;
+mod text;
+
+pub use chat::*;
+pub use codec::*;
+pub use compression::*;
+pub use content::*;
+pub use encoding::*;
+pub use media::*;
+pub use message::*;
+pub use server_type::*;
+pub use text::*;
@@ -0,0 +1,48 @@
+use crate::{DroneSend, Request, Response};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum MediaRequest {
+    MediaList,
+    /// `media?(media_id)`
+    Media(u64),
+}
+
+impl DroneSend for MediaRequest {}
+impl Request for MediaRequest {}
+
+/// A media file with its MIME type, such as `image/png`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub struct MediaFile {
+    pub content_type: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    pub data: Vec<u8>,
+}
+
+impl MediaFile {
+    pub fn new(content_type: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            content_type: content_type.into(),
+            size: data.len() as u64,
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum MediaResponse {
+    MediaList(Vec<u64>),
+    /// `media!(media)`
+    Media(MediaFile),
+    /// `error_requested_not_found!`
+    NotFound,
+    /// `error_unsupported_request!`
+    UnsupportedRequest,
+}
+
+impl DroneSend for MediaResponse {}
+impl Response for MediaResponse {}
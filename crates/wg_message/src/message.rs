@@ -0,0 +1,29 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wg_network::NodeId;
+
+/// A high-level message exchanged between clients and servers,
+/// sent over the network split in fragments.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub struct Message<M: DroneSend> {
+    pub source_id: NodeId,
+    pub session_id: u64,
+    pub content: M,
+}
+
+/// Content that can be sent through the drones, in any [`crate::MessageEncoding`].
+pub trait DroneSend: Serialize + DeserializeOwned {
+    /// Returns the content as JSON, without the tag of [`crate::MessageEncoding::Json`].
+    fn stringify(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+    fn from_string(raw: String) -> Result<Self, String> {
+        serde_json::from_str(raw.as_str()).map_err(|e| e.to_string())
+    }
+}
+
+/// Content sent by clients to servers.
+pub trait Request: DroneSend {}
+/// Content sent by servers to clients.
+pub trait Response: DroneSend {}
@@ -0,0 +1,42 @@
+use crate::{DroneSend, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// The kind of service a server offers, answered to `server_type?`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ServerType {
+    Text,
+    Media,
+    Chat,
+    /// A service outside of the protocol, with the version of its requests,
+    /// so clients can tell apart servers they cannot talk to.
+    Custom {
+        name: String,
+        version: u32,
+    },
+}
+
+impl Display for ServerType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerType::Text => write!(f, "text"),
+            ServerType::Media => write!(f, "media"),
+            ServerType::Chat => write!(f, "chat"),
+            ServerType::Custom { name, version } => write!(f, "{} v{}", name, version),
+        }
+    }
+}
+
+/// `server_type?`, understood by every server whatever its requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerTypeRequest;
+
+impl DroneSend for ServerTypeRequest {}
+impl Request for ServerTypeRequest {}
+
+/// `server_type!(type)`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerTypeResponse(pub ServerType);
+
+impl DroneSend for ServerTypeResponse {}
+impl Response for ServerTypeResponse {}
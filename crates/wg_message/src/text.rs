@@ -0,0 +1,30 @@
+use crate::{DroneSend, Request, Response};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum TextRequest {
+    /// `files_list?`
+    TextList,
+    /// `file?(file_id)`
+    Text(u64),
+}
+
+impl DroneSend for TextRequest {}
+impl Request for TextRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(PartialEq))]
+pub enum TextResponse {
+    /// `files_list!(list_of_file_ids)`
+    TextList(Vec<u64>),
+    /// `file!(file_size, file)`, without the size: it is the length of the text once received
+    Text(String),
+    /// `error_requested_not_found!`
+    NotFound,
+    /// `error_unsupported_request!`
+    UnsupportedRequest,
+}
+
+impl DroneSend for TextResponse {}
+impl Response for TextResponse {}
@@ -0,0 +1,414 @@
+use crate::{NodeId, NodeType, Topology};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+
+/// How many sets of drones [`Topology::max_crash_set`] looks at before settling for the
+/// largest found.
+const CRASH_SEARCH_BUDGET: usize = 20_000;
+
+/// An invariant of the network that does not hold, see [`Topology::violations`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// The drones are split into `components` groups that cannot reach each other.
+    DronesSplit { components: usize },
+    /// A client must be connected to at least one drone.
+    ClientLinks { client: NodeId, count: usize },
+    /// A server must be connected to at least two drones.
+    ServerLinks { server: NodeId, count: usize },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::DronesSplit { components } => {
+                write!(f, "the drones are split into {} groups", components)
+            }
+            Violation::ClientLinks { client, count } => {
+                write!(f, "client {} is connected to {} drones", client, count)
+            }
+            Violation::ServerLinks { server, count } => {
+                write!(f, "server {} is connected to {} drones", server, count)
+            }
+        }
+    }
+}
+
+/// What is taken out of the topology when looking for violations.
+#[derive(Default)]
+struct Removed<'a> {
+    nodes: Option<&'a HashSet<NodeId>>,
+    edge: Option<(NodeId, NodeId)>,
+}
+
+impl Removed<'_> {
+    fn node(&self, id: NodeId) -> bool {
+        self.nodes.is_some_and(|nodes| nodes.contains(&id))
+    }
+    fn edge(&self, a: NodeId, b: NodeId) -> bool {
+        self.edge == Some((a.min(b), a.max(b)))
+    }
+}
+
+impl Topology {
+    // INVARIANTS
+    /// Returns the invariants that do not hold in the topology, the same checked on the
+    /// Network Initialization File: the drones are connected among themselves,
+    /// every client has at least one drone and every server at least two.
+    ///
+    /// Clients and servers do not forward packets, so they never connect two drones.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.violations_without(&self.drone_adjacency(), &Removed::default())
+    }
+    /// Returns the violations that removing the node, for example by crashing it, would introduce.
+    /// An empty list means the removal is safe; nothing happens if the node is not present.
+    pub fn what_if_remove(&self, node: NodeId) -> Vec<Violation> {
+        let adjacency = self.drone_adjacency();
+        let before = self.violations_without(&adjacency, &Removed::default());
+        let nodes = HashSet::from([node]);
+        let removed = Removed {
+            nodes: Some(&nodes),
+            edge: None,
+        };
+        self.new_violations(&adjacency, &removed, &before)
+    }
+    /// Returns the violations that removing the link `a - b`, for example with
+    /// `RemoveSender`, would introduce. An empty list means the removal is safe.
+    pub fn what_if_remove_link(&self, a: NodeId, b: NodeId) -> Vec<Violation> {
+        let adjacency = self.drone_adjacency();
+        let before = self.violations_without(&adjacency, &Removed::default());
+        let removed = Removed {
+            nodes: None,
+            edge: Some((a.min(b), a.max(b))),
+        };
+        self.new_violations(&adjacency, &removed, &before)
+    }
+
+    // CRITICAL NODES AND LINKS
+    /// Returns the drones whose removal splits the network: either the remaining drones
+    /// cannot all reach each other, or a client or server is left without drones.
+    /// The list is sorted.
+    pub fn articulation_drones(&self) -> Vec<NodeId> {
+        let adjacency = self.drone_adjacency();
+        let (mut articulations, _) = Self::articulations_and_bridges(&adjacency);
+        articulations.extend(
+            self.hosts()
+                .filter_map(|(id, _)| self.only_drone(&adjacency, id)),
+        );
+        let mut articulations = articulations.into_iter().collect::<Vec<_>>();
+        articulations.sort();
+        articulations
+    }
+    /// Returns the links whose removal splits the network, with the same meaning as in
+    /// [`Topology::articulation_drones`]. The smaller id is first and the list is sorted.
+    pub fn bridges(&self) -> Vec<(NodeId, NodeId)> {
+        let adjacency = self.drone_adjacency();
+        let (_, mut bridges) = Self::articulations_and_bridges(&adjacency);
+        bridges.extend(self.hosts().filter_map(|(id, _)| {
+            let drone = self.only_drone(&adjacency, id)?;
+            Some((id.min(drone), id.max(drone)))
+        }));
+        let mut bridges = bridges.into_iter().collect::<Vec<_>>();
+        bridges.sort();
+        bridges
+    }
+    /// Returns the drones that can be crashed right now without introducing violations.
+    /// The list is sorted.
+    pub fn crashable_drones(&self) -> Vec<NodeId> {
+        let adjacency = self.drone_adjacency();
+        let before = self.violations_without(&adjacency, &Removed::default());
+        let mut drones = adjacency.keys().cloned().collect::<Vec<_>>();
+        drones.sort();
+        drones.retain(|&drone| {
+            let nodes = HashSet::from([drone]);
+            let removed = Removed {
+                nodes: Some(&nodes),
+                edge: None,
+            };
+            self.new_violations(&adjacency, &removed, &before)
+                .is_empty()
+        });
+        drones
+    }
+    /// Returns the maximum number of drones that can be crashed at the same time
+    /// without introducing violations.
+    pub fn max_simultaneous_crashes(&self) -> usize {
+        self.max_crash_set().len()
+    }
+    /// Returns a large set of drones that can be crashed at the same time without
+    /// introducing violations, sorted.
+    ///
+    /// It starts from the set found greedily, crashing the drones in order while allowed,
+    /// then searches for larger ones. The search is exact on small networks, on larger ones
+    /// it gives up after a fixed number of sets, so the result can be below the maximum.
+    pub fn max_crash_set(&self) -> Vec<NodeId> {
+        let adjacency = self.drone_adjacency();
+        let before = self.violations_without(&adjacency, &Removed::default());
+        // drones that are the last link of a client or server can never be crashed
+        let mut candidates = adjacency.keys().cloned().collect::<Vec<_>>();
+        candidates.sort();
+        candidates.retain(|&drone| {
+            let nodes = HashSet::from([drone]);
+            self.new_host_violations(&adjacency, &nodes, &before)
+                .is_empty()
+        });
+
+        let mut chosen = HashSet::new();
+        for &drone in candidates.iter() {
+            chosen.insert(drone);
+            let removed = Removed {
+                nodes: Some(&chosen),
+                edge: None,
+            };
+            if !self
+                .new_violations(&adjacency, &removed, &before)
+                .is_empty()
+            {
+                chosen.remove(&drone);
+            }
+        }
+        let mut best = chosen.into_iter().collect::<Vec<_>>();
+        let mut chosen = HashSet::new();
+        let mut budget = CRASH_SEARCH_BUDGET;
+        self.search_crash_set(
+            &adjacency,
+            &before,
+            &candidates,
+            &mut chosen,
+            &mut best,
+            &mut budget,
+        );
+        best.sort();
+        best
+    }
+    fn search_crash_set(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        before: &[Violation],
+        candidates: &[NodeId],
+        chosen: &mut HashSet<NodeId>,
+        best: &mut Vec<NodeId>,
+        budget: &mut usize,
+    ) {
+        if chosen.len() + candidates.len() <= best.len() || *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        let Some((&drone, rest)) = candidates.split_first() else {
+            return;
+        };
+        chosen.insert(drone);
+        // removing more drones only lowers the links of clients and servers, so a violation
+        // there cannot be fixed further down; a split of the drones instead can
+        if self
+            .new_host_violations(adjacency, chosen, before)
+            .is_empty()
+        {
+            let removed = Removed {
+                nodes: Some(chosen),
+                edge: None,
+            };
+            if chosen.len() > best.len()
+                && self.new_violations(adjacency, &removed, before).is_empty()
+            {
+                *best = chosen.iter().cloned().collect();
+            }
+            self.search_crash_set(adjacency, before, rest, chosen, best, budget);
+        }
+        chosen.remove(&drone);
+        self.search_crash_set(adjacency, before, rest, chosen, best, budget);
+    }
+
+    // OTHERS
+    /// Returns the drones with their drone neighbors.
+    fn drone_adjacency(&self) -> HashMap<NodeId, Vec<NodeId>> {
+        let mut adjacency = self
+            .nodes()
+            .filter(|&(_, node_type)| node_type == NodeType::Drone)
+            .map(|(id, _)| (id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for ((a, b), _) in self.edges() {
+            if adjacency.contains_key(&a) && adjacency.contains_key(&b) {
+                adjacency.entry(a).or_default().push(b);
+                adjacency.entry(b).or_default().push(a);
+            }
+        }
+        for neighbors in adjacency.values_mut() {
+            neighbors.sort();
+        }
+        adjacency
+    }
+    /// Returns the clients and the servers, sorted by id.
+    fn hosts(&self) -> impl Iterator<Item = (NodeId, NodeType)> {
+        let mut hosts = self
+            .nodes()
+            .filter(|&(_, node_type)| node_type != NodeType::Drone)
+            .collect::<Vec<_>>();
+        hosts.sort_by_key(|&(id, _)| id);
+        hosts.into_iter()
+    }
+    /// Returns the drones connected to the client or server, excluding the removed ones.
+    fn host_drones(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        host: NodeId,
+        removed: &Removed,
+    ) -> usize {
+        self.neighbors(host)
+            .into_iter()
+            .filter(|drone| adjacency.contains_key(drone))
+            .filter(|&drone| !removed.node(drone) && !removed.edge(host, drone))
+            .count()
+    }
+    /// Returns the drone the client or server depends on, if it has exactly one.
+    fn only_drone(&self, adjacency: &HashMap<NodeId, Vec<NodeId>>, host: NodeId) -> Option<NodeId> {
+        let mut drones = self
+            .neighbors(host)
+            .into_iter()
+            .filter(|drone| adjacency.contains_key(drone));
+        match (drones.next(), drones.next()) {
+            (Some(drone), None) => Some(drone),
+            _ => None,
+        }
+    }
+    fn violations_without(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        removed: &Removed,
+    ) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let components = Self::drone_components(adjacency, removed);
+        if components > 1 {
+            violations.push(Violation::DronesSplit { components });
+        }
+        violations.extend(self.host_violations(adjacency, removed));
+        violations
+    }
+    fn host_violations(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        removed: &Removed,
+    ) -> Vec<Violation> {
+        self.hosts()
+            .filter(|&(id, _)| !removed.node(id))
+            .filter_map(|(id, node_type)| {
+                let count = self.host_drones(adjacency, id, removed);
+                match node_type {
+                    NodeType::Client if count < 1 => {
+                        Some(Violation::ClientLinks { client: id, count })
+                    }
+                    NodeType::Server if count < 2 => {
+                        Some(Violation::ServerLinks { server: id, count })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    /// Returns the violations after the removal that were not there `before`.
+    fn new_violations(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        removed: &Removed,
+        before: &[Violation],
+    ) -> Vec<Violation> {
+        let mut after = self.violations_without(adjacency, removed);
+        after.retain(|violation| !before.contains(violation));
+        after
+    }
+    fn new_host_violations(
+        &self,
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+        nodes: &HashSet<NodeId>,
+        before: &[Violation],
+    ) -> Vec<Violation> {
+        let removed = Removed {
+            nodes: Some(nodes),
+            edge: None,
+        };
+        let mut after = self.host_violations(adjacency, &removed);
+        after.retain(|violation| !before.contains(violation));
+        after
+    }
+    /// Returns the number of groups of drones that cannot reach each other.
+    fn drone_components(adjacency: &HashMap<NodeId, Vec<NodeId>>, removed: &Removed) -> usize {
+        let mut reached = HashSet::new();
+        let mut components = 0;
+        for &start in adjacency.keys() {
+            if removed.node(start) || !reached.insert(start) {
+                continue;
+            }
+            components += 1;
+            let mut queue = VecDeque::from([start]);
+            while let Some(id) = queue.pop_front() {
+                for &neighbor in adjacency[&id].iter() {
+                    if !removed.node(neighbor)
+                        && !removed.edge(id, neighbor)
+                        && reached.insert(neighbor)
+                    {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        components
+    }
+    /// Returns the articulation points and the bridges among the drones, with Tarjan's algorithm.
+    fn articulations_and_bridges(
+        adjacency: &HashMap<NodeId, Vec<NodeId>>,
+    ) -> (HashSet<NodeId>, HashSet<(NodeId, NodeId)>) {
+        let mut articulations = HashSet::new();
+        let mut bridges = HashSet::new();
+        let mut discovery = HashMap::new();
+        let mut low = HashMap::new();
+        let mut roots = adjacency.keys().cloned().collect::<Vec<_>>();
+        roots.sort();
+        for root in roots {
+            if discovery.contains_key(&root) {
+                continue;
+            }
+            discovery.insert(root, discovery.len());
+            low.insert(root, discovery[&root]);
+            let mut root_children = 0;
+            // (node, parent, index of the next neighbor to visit)
+            let mut stack = vec![(root, None, 0)];
+            while let Some((id, parent, next)) = stack.pop() {
+                if let Some(&neighbor) = adjacency[&id].get(next) {
+                    stack.push((id, parent, next + 1));
+                    if Some(neighbor) == parent {
+                        continue;
+                    }
+                    match discovery.get(&neighbor) {
+                        Some(&neighbor_discovery) => {
+                            let id_low = low[&id];
+                            low.insert(id, id_low.min(neighbor_discovery));
+                        }
+                        None => {
+                            discovery.insert(neighbor, discovery.len());
+                            low.insert(neighbor, discovery[&neighbor]);
+                            stack.push((neighbor, Some(id), 0));
+                        }
+                    }
+                    continue;
+                }
+                // all the neighbors are visited, report to the parent
+                let Some(parent) = parent else {
+                    continue;
+                };
+                let (id_low, parent_low) = (low[&id], low[&parent]);
+                low.insert(parent, parent_low.min(id_low));
+                if id_low > discovery[&parent] {
+                    bridges.insert((parent.min(id), parent.max(id)));
+                }
+                if parent == root {
+                    root_children += 1;
+                } else if id_low >= discovery[&parent] {
+                    articulations.insert(parent);
+                }
+            }
+            if root_children > 1 {
+                articulations.insert(root);
+            }
+        }
+        (articulations, bridges)
+    }
+}
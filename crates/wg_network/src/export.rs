@@ -0,0 +1,298 @@
+use crate::{NodeId, NodeType, Topology};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const SVG_WIDTH: f32 = 800.0;
+const SVG_HEIGHT: f32 = 600.0;
+const SVG_MARGIN: f32 = 40.0;
+const NODE_RADIUS: f32 = 16.0;
+const LAYOUT_ITERATIONS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub id: NodeId,
+    pub node_type: NodeType,
+    pub pdr: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphEdge {
+    pub a: NodeId,
+    pub b: NodeId,
+    /// Number of packets that crossed the edge, in any direction.
+    pub traffic: u64,
+}
+
+/// A network ready to be drawn, either as Graphviz DOT or directly as SVG.
+/// It can be created from a [`Topology`], from a config or from a controller snapshot.
+///
+/// Nodes are coloured by type and labelled with their pdr when known,
+/// edges are thicker the more traffic they carried.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+impl From<&Topology> for NetworkGraph {
+    fn from(topology: &Topology) -> Self {
+        let mut graph = NetworkGraph::new();
+        for (id, node_type) in topology.nodes() {
+            graph.add_node(id, node_type, None);
+        }
+        for ((a, b), _) in topology.edges() {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+}
+
+impl NetworkGraph {
+    // INITIALIZATION
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a node or updates it.
+    pub fn add_node(&mut self, id: NodeId, node_type: NodeType, pdr: Option<f32>) {
+        let node = GraphNode { id, node_type, pdr };
+        match self.nodes.binary_search_by_key(&id, |node| node.id) {
+            Ok(i) => self.nodes[i] = node,
+            Err(i) => self.nodes.insert(i, node),
+        }
+    }
+    /// Adds the undirected edge `a - b` if not present.
+    pub fn add_edge(&mut self, a: NodeId, b: NodeId) {
+        let (a, b) = (a.min(b), a.max(b));
+        if let Err(i) = self
+            .edges
+            .binary_search_by_key(&(a, b), |edge| (edge.a, edge.b))
+        {
+            self.edges.insert(i, GraphEdge { a, b, traffic: 0 });
+        }
+    }
+    /// Adds `count` packets to the traffic of the edge `a - b`, adding the edge if needed.
+    pub fn add_traffic(&mut self, a: NodeId, b: NodeId, count: u64) {
+        self.add_edge(a, b);
+        let key = (a.min(b), a.max(b));
+        if let Ok(i) = self
+            .edges
+            .binary_search_by_key(&key, |edge| (edge.a, edge.b))
+        {
+            self.edges[i].traffic += count;
+        }
+    }
+    /// Returns the nodes, sorted by id.
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+    /// Returns the edges, sorted, with the smaller id first.
+    pub fn edges(&self) -> &[GraphEdge] {
+        &self.edges
+    }
+
+    // DOT
+    /// Returns the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("graph network {\n    node [style=filled, fontname=\"sans-serif\"];\n");
+        for node in self.nodes.iter() {
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{}\", shape={}, fillcolor=\"{}\"];",
+                node.id,
+                Self::label(node).replace('\n', "\\n"),
+                match node.node_type {
+                    NodeType::Drone => "circle",
+                    _ => "box",
+                },
+                Self::color(node.node_type)
+            );
+        }
+        let max_traffic = self.max_traffic();
+        for edge in self.edges.iter() {
+            if edge.traffic > 0 {
+                let _ = writeln!(
+                    dot,
+                    "    {} -- {} [label=\"{}\", penwidth={:.1}];",
+                    edge.a,
+                    edge.b,
+                    edge.traffic,
+                    Self::stroke_width(edge, max_traffic)
+                );
+            } else {
+                let _ = writeln!(dot, "    {} -- {};", edge.a, edge.b);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // SVG
+    /// Returns the graph as an SVG image, laid out with [`NetworkGraph::layout`].
+    pub fn to_svg(&self) -> String {
+        let positions = self.layout(SVG_WIDTH, SVG_HEIGHT);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+            SVG_WIDTH, SVG_HEIGHT
+        );
+        svg.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+        let max_traffic = self.max_traffic();
+        for edge in self.edges.iter() {
+            let (Some(&(x1, y1)), Some(&(x2, y2))) =
+                (positions.get(&edge.a), positions.get(&edge.b))
+            else {
+                continue;
+            };
+            let _ = writeln!(
+                svg,
+                "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#555555\" stroke-width=\"{:.1}\"/>",
+                x1, y1, x2, y2, Self::stroke_width(edge, max_traffic)
+            );
+            if edge.traffic > 0 {
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"#555555\">{}</text>",
+                    (x1 + x2) / 2.0,
+                    (y1 + y2) / 2.0 - 3.0,
+                    edge.traffic
+                );
+            }
+        }
+        for node in self.nodes.iter() {
+            let (x, y) = positions[&node.id];
+            let shape = match node.node_type {
+                NodeType::Drone => format!(
+                    "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\"",
+                    x, y, NODE_RADIUS
+                ),
+                _ => format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{}\" rx=\"3\"",
+                    x - NODE_RADIUS,
+                    y - NODE_RADIUS,
+                    NODE_RADIUS * 2.0,
+                    NODE_RADIUS * 2.0
+                ),
+            };
+            let _ = writeln!(
+                svg,
+                "  {} fill=\"{}\" stroke=\"#333333\"/>",
+                shape,
+                Self::color(node.node_type)
+            );
+            for (i, line) in Self::label(node).lines().enumerate() {
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                    x,
+                    y + 4.0 + i as f32 * (NODE_RADIUS + 10.0),
+                    line
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+    /// Computes the position of every node with a force-directed layout (Fruchterman-Reingold).
+    /// The layout is deterministic: the same graph is always drawn the same way.
+    pub fn layout(&self, width: f32, height: f32) -> HashMap<NodeId, (f32, f32)> {
+        let n = self.nodes.len();
+        let (center_x, center_y) = (width / 2.0, height / 2.0);
+        let radius = (width.min(height) / 2.0 - SVG_MARGIN).max(1.0);
+        // nodes start on a circle, sorted by id
+        let mut positions = (0..n)
+            .map(|i| {
+                let angle = i as f32 / n.max(1) as f32 * std::f32::consts::TAU;
+                (
+                    center_x + radius * angle.cos(),
+                    center_y + radius * angle.sin(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let index = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id, i))
+            .collect::<HashMap<_, _>>();
+
+        let k = ((width * height) / n.max(1) as f32).sqrt() * 0.5;
+        for iteration in 0..LAYOUT_ITERATIONS {
+            let temperature = width / 10.0 * (1.0 - iteration as f32 / LAYOUT_ITERATIONS as f32);
+            let mut displacement = vec![(0.0f32, 0.0f32); n];
+            // every pair of nodes repels
+            for i in 0..n {
+                for j in i + 1..n {
+                    let (dx, dy) = (
+                        positions[i].0 - positions[j].0,
+                        positions[i].1 - positions[j].1,
+                    );
+                    let distance = (dx * dx + dy * dy).sqrt().max(0.01);
+                    let force = k * k / distance;
+                    displacement[i].0 += dx / distance * force;
+                    displacement[i].1 += dy / distance * force;
+                    displacement[j].0 -= dx / distance * force;
+                    displacement[j].1 -= dy / distance * force;
+                }
+            }
+            // connected nodes attract
+            for edge in self.edges.iter() {
+                let (Some(&i), Some(&j)) = (index.get(&edge.a), index.get(&edge.b)) else {
+                    continue;
+                };
+                let (dx, dy) = (
+                    positions[i].0 - positions[j].0,
+                    positions[i].1 - positions[j].1,
+                );
+                let distance = (dx * dx + dy * dy).sqrt().max(0.01);
+                let force = distance * distance / k;
+                displacement[i].0 -= dx / distance * force;
+                displacement[i].1 -= dy / distance * force;
+                displacement[j].0 += dx / distance * force;
+                displacement[j].1 += dy / distance * force;
+            }
+            for (position, (dx, dy)) in positions.iter_mut().zip(displacement) {
+                let length = (dx * dx + dy * dy).sqrt().max(0.01);
+                let step = length.min(temperature);
+                position.0 =
+                    (position.0 + dx / length * step).clamp(SVG_MARGIN, width - SVG_MARGIN);
+                position.1 =
+                    (position.1 + dy / length * step).clamp(SVG_MARGIN, height - SVG_MARGIN);
+            }
+        }
+
+        self.nodes
+            .iter()
+            .zip(positions)
+            .map(|(node, position)| (node.id, position))
+            .collect()
+    }
+
+    // OTHERS
+    fn label(node: &GraphNode) -> String {
+        match node.pdr {
+            Some(pdr) => format!("{}\npdr {}", node.id, pdr),
+            None => format!("{}\n{:?}", node.id, node.node_type),
+        }
+    }
+    fn color(node_type: NodeType) -> &'static str {
+        match node_type {
+            NodeType::Client => "#8ecae6",
+            NodeType::Drone => "#ffb703",
+            NodeType::Server => "#90be6d",
+        }
+    }
+    fn max_traffic(&self) -> u64 {
+        self.edges
+            .iter()
+            .map(|edge| edge.traffic)
+            .max()
+            .unwrap_or(0)
+    }
+    /// Returns the width of the edge, relative to the busiest one.
+    fn stroke_width(edge: &GraphEdge, max_traffic: u64) -> f32 {
+        if max_traffic == 0 {
+            return 1.0;
+        }
+        1.0 + 4.0 * edge.traffic as f32 / max_traffic as f32
+    }
+}
@@ -1,3 +1,11 @@
+mod analysis;
+mod export;
+mod multipath;
 mod routing;
+mod topology;
 
+pub use analysis::*;
+pub use export::*;
+pub use multipath::*;
 pub use routing::*;
+pub use topology::*;
@@ -0,0 +1,215 @@
+use crate::{NodeId, NodeType, SourceRoutingHeader, Topology};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+/// What the routes returned by [`Topology::disjoint_routes`] must not share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disjointness {
+    /// No intermediate node is shared, hence no link either.
+    Node,
+    /// No link is shared, intermediate nodes can be.
+    Edge,
+}
+
+impl Topology {
+    /// Returns one of the shortest routes from `from` to `to`, with the hop index set to 1.
+    /// Only drones are used as intermediate nodes, since clients and servers do not forward packets.
+    pub fn shortest_route(&self, from: NodeId, to: NodeId) -> Option<SourceRoutingHeader> {
+        if from == to || !self.contains_node(from) || !self.contains_node(to) {
+            return None;
+        }
+        let mut previous = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut hops = vec![to];
+                let mut hop = to;
+                while hop != from {
+                    hop = previous[&hop];
+                    hops.push(hop);
+                }
+                hops.reverse();
+                return Some(SourceRoutingHeader::with_first_hop(hops));
+            }
+            if node != from && self.node_type(node) != Some(NodeType::Drone) {
+                continue;
+            }
+            let mut neighbors = self.neighbors(node).into_iter().collect::<Vec<_>>();
+            neighbors.sort();
+            for neighbor in neighbors {
+                if let Entry::Vacant(entry) = previous.entry(neighbor) {
+                    entry.insert(node);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns up to `k` routes from `from` to `to` that are pairwise node or edge disjoint,
+    /// shortest first, with the hop index set to 1.
+    /// Only drones are used as intermediate nodes.
+    ///
+    /// The routes are found as a maximum flow, so if `k` disjoint routes exist `k` are returned,
+    /// even when greedily taking the shortest one would block the others.
+    pub fn disjoint_routes(
+        &self,
+        from: NodeId,
+        to: NodeId,
+        k: usize,
+        disjointness: Disjointness,
+    ) -> Vec<SourceRoutingHeader> {
+        if k == 0 || from == to || !self.contains_node(from) || !self.contains_node(to) {
+            return Vec::new();
+        }
+
+        let mut ids = self.nodes().map(|(id, _)| id).collect::<Vec<_>>();
+        ids.sort();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i))
+            .collect::<HashMap<_, _>>();
+        // every node is split in an "in" (2i) and an "out" (2i + 1) vertex
+        let mut network = FlowNetwork::new(ids.len() * 2);
+        let internal = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| {
+                let capacity = match (id == from || id == to, self.node_type(id)) {
+                    (true, _) => k,
+                    (false, Some(NodeType::Drone)) if disjointness == Disjointness::Edge => k,
+                    (false, Some(NodeType::Drone)) => 1,
+                    (false, _) => 0,
+                };
+                network.add_arc(2 * i, 2 * i + 1, capacity)
+            })
+            .collect::<Vec<_>>();
+        let mut edges = self.edges().map(|(edge, _)| edge).collect::<Vec<_>>();
+        edges.sort();
+        let links = edges
+            .into_iter()
+            .map(|(a, b)| {
+                let (a, b) = (index[&a], index[&b]);
+                (
+                    (a, network.add_arc(2 * a + 1, 2 * b, 1)),
+                    (b, network.add_arc(2 * b + 1, 2 * a, 1)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let source = 2 * index[&from] + 1;
+        let sink = 2 * index[&to];
+        let mut found = 0;
+        while found < k && network.augment(source, sink) {
+            found += 1;
+        }
+        if disjointness == Disjointness::Edge {
+            // a link used in both directions is the same as not using it at all
+            for ((a, a_to_b), (b, b_to_a)) in links {
+                let cancelled = network.flow[a_to_b].min(network.flow[b_to_a]);
+                for arc in [a_to_b, b_to_a, internal[a], internal[b]] {
+                    network.flow[arc] -= cancelled;
+                }
+            }
+        }
+
+        let mut routes = (0..found)
+            .filter_map(|_| network.take_path(source, sink))
+            .map(|vertices| {
+                let mut hops = vec![from];
+                hops.extend(
+                    vertices
+                        .into_iter()
+                        .filter(|v| v & 1 == 0)
+                        .map(|v| ids[v / 2]),
+                );
+                SourceRoutingHeader::with_first_hop(hops).without_loops()
+            })
+            .collect::<Vec<_>>();
+        routes.sort_by_key(|route| route.len());
+        routes
+    }
+}
+
+/// Directed flow network with unit augmentations.
+/// Arcs are stored in pairs: `i` is a real arc when even, and `i ^ 1` is its residual reverse.
+#[derive(Debug, Clone)]
+struct FlowNetwork {
+    targets: Vec<usize>,
+    capacity: Vec<usize>,
+    flow: Vec<usize>,
+    outgoing: Vec<Vec<usize>>,
+}
+
+impl FlowNetwork {
+    fn new(vertices: usize) -> Self {
+        Self {
+            targets: Vec::new(),
+            capacity: Vec::new(),
+            flow: Vec::new(),
+            outgoing: vec![Vec::new(); vertices],
+        }
+    }
+    /// Adds an arc returning its index.
+    fn add_arc(&mut self, from: usize, to: usize, capacity: usize) -> usize {
+        let arc = self.targets.len();
+        self.outgoing[from].push(arc);
+        self.outgoing[to].push(arc + 1);
+        self.targets.extend([to, from]);
+        self.capacity.extend([capacity, 0]);
+        self.flow.extend([0, 0]);
+        arc
+    }
+    fn residual(&self, arc: usize) -> usize {
+        if arc & 1 == 0 {
+            self.capacity[arc] - self.flow[arc]
+        } else {
+            self.flow[arc ^ 1]
+        }
+    }
+    /// Pushes one unit of flow along a shortest augmenting path, if any.
+    fn augment(&mut self, source: usize, sink: usize) -> bool {
+        let mut through = vec![None; self.outgoing.len()];
+        let mut queue = VecDeque::from([source]);
+        while let Some(vertex) = queue.pop_front() {
+            if vertex == sink {
+                break;
+            }
+            for &arc in self.outgoing[vertex].iter() {
+                let to = self.targets[arc];
+                if to != source && through[to].is_none() && self.residual(arc) > 0 {
+                    through[to] = Some(arc);
+                    queue.push_back(to);
+                }
+            }
+        }
+        if through[sink].is_none() {
+            return false;
+        }
+        let mut vertex = sink;
+        while let Some(arc) = through[vertex] {
+            if arc & 1 == 0 {
+                self.flow[arc] += 1;
+            } else {
+                self.flow[arc ^ 1] -= 1;
+            }
+            vertex = self.targets[arc ^ 1];
+        }
+        true
+    }
+    /// Removes one unit of flow from `source` to `sink`, returning the visited vertices.
+    fn take_path(&mut self, source: usize, sink: usize) -> Option<Vec<usize>> {
+        let mut vertices = Vec::new();
+        let mut vertex = source;
+        while vertex != sink {
+            let arc = *self.outgoing[vertex]
+                .iter()
+                .find(|&&arc| arc & 1 == 0 && self.flow[arc] > 0)?;
+            self.flow[arc] -= 1;
+            vertex = self.targets[arc];
+            vertices.push(vertex);
+        }
+        Some(vertices)
+    }
+}
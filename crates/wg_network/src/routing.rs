@@ -1,8 +1,42 @@
-use std::collections::Bound;
+use std::collections::{Bound, HashSet};
+use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::RangeBounds;
 
+/// Identifier of a node, `u8` unless one of the `wide-node-id` features is enabled.
+#[cfg(not(feature = "wide-node-id"))]
 pub type NodeId = u8;
+#[cfg(all(feature = "wide-node-id", not(feature = "wide-node-id-32")))]
+pub type NodeId = u16;
+#[cfg(feature = "wide-node-id-32")]
+pub type NodeId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingError {
+    EmptyRoute,
+    HopIndexUnderflow,
+    HopIndexOutOfRange { hop_index: usize, len: usize },
+    SelfHop(NodeId), // the node is repeated in two consecutive hops
+    Loop(NodeId),    // the node appears more than once in the route
+}
+
+impl Display for RoutingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingError::EmptyRoute => write!(f, "the route is empty"),
+            RoutingError::HopIndexUnderflow => write!(f, "the hop index is already 0"),
+            RoutingError::HopIndexOutOfRange { hop_index, len } => write!(
+                f,
+                "the hop index {} is out of range for a route of {} hops",
+                hop_index, len
+            ),
+            RoutingError::SelfHop(id) => write!(f, "node {} hops to itself", id),
+            RoutingError::Loop(id) => write!(f, "node {} appears more than once", id),
+        }
+    }
+}
+
+impl Error for RoutingError {}
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "debug", derive(PartialEq))]
@@ -61,9 +95,32 @@ impl SourceRoutingHeader {
         self.hop_index += 1;
     }
     /// Decreases the hop index by 1.
+    /// **Panics if the hop index is 0**, see [`SourceRoutingHeader::try_decrease_hop_index`].
     pub fn decrease_hop_index(&mut self) {
         self.hop_index -= 1;
     }
+    /// Increases the hop index by 1, failing if it would go past the destination.
+    pub fn try_increase_hop_index(&mut self) -> Result<(), RoutingError> {
+        let out_of_range = RoutingError::HopIndexOutOfRange {
+            hop_index: self.hop_index.saturating_add(1),
+            len: self.hops.len(),
+        };
+        match self.hop_index.checked_add(1) {
+            Some(hop_index) if hop_index < self.hops.len() => {
+                self.hop_index = hop_index;
+                Ok(())
+            }
+            _ => Err(out_of_range),
+        }
+    }
+    /// Decreases the hop index by 1, failing if it is already 0.
+    pub fn try_decrease_hop_index(&mut self) -> Result<(), RoutingError> {
+        self.hop_index = self
+            .hop_index
+            .checked_sub(1)
+            .ok_or(RoutingError::HopIndexUnderflow)?;
+        Ok(())
+    }
     /// Resets the hop index to 0.
     pub fn reset_hop_index(&mut self) {
         self.hop_index = 0;
@@ -84,14 +141,19 @@ impl SourceRoutingHeader {
     }
     /// Returns the next hop of the route if present.
     pub fn next_hop(&self) -> Option<NodeId> {
-        self.hops.get(self.hop_index + 1).cloned()
+        self.hops.get(self.hop_index.checked_add(1)?).cloned()
     }
     /// Returns the previous hop of the route if present.
     pub fn previous_hop(&self) -> Option<NodeId> {
-        if self.is_first_hop() {
-            return None;
-        }
-        self.hops.get(self.hop_index - 1).cloned()
+        self.hops.get(self.hop_index.checked_sub(1)?).cloned()
+    }
+    /// Returns true if the route contains the node.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.hops.contains(&id)
+    }
+    /// Returns the position of the first occurrence of the node in the route if present.
+    pub fn position_of(&self, id: NodeId) -> Option<usize> {
+        self.hops.iter().position(|&hop| hop == id)
     }
 
     // CHECKS
@@ -111,6 +173,39 @@ impl SourceRoutingHeader {
     pub fn valid_hop_index(&self) -> bool {
         !self.is_empty() && self.hop_index < self.hops.len()
     }
+    /// Returns true if some node appears more than once in the route.
+    pub fn has_loops(&self) -> bool {
+        self.first_repeated_hop().is_some()
+    }
+    /// Returns true if some node hops to itself.
+    pub fn has_self_hops(&self) -> bool {
+        self.hops.windows(2).any(|pair| pair[0] == pair[1])
+    }
+    /// Returns the first node that appears more than once in the route if present.
+    pub fn first_repeated_hop(&self) -> Option<NodeId> {
+        let mut seen = HashSet::new();
+        self.hops.iter().find(|&&hop| !seen.insert(hop)).cloned()
+    }
+    /// Checks that the route is not empty, that the hop index is valid and that there are no loops.
+    /// Useful to check headers received from other nodes before using them.
+    pub fn validate(&self) -> Result<(), RoutingError> {
+        if self.is_empty() {
+            return Err(RoutingError::EmptyRoute);
+        }
+        if !self.valid_hop_index() {
+            return Err(RoutingError::HopIndexOutOfRange {
+                hop_index: self.hop_index,
+                len: self.hops.len(),
+            });
+        }
+        if let Some(pair) = self.hops.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(RoutingError::SelfHop(pair[0]));
+        }
+        match self.first_repeated_hop() {
+            Some(id) => Err(RoutingError::Loop(id)),
+            None => Ok(()),
+        }
+    }
 
     // HOPS MANIPULATION
     /// Appends a hop to the route.
@@ -120,6 +215,7 @@ impl SourceRoutingHeader {
 
     // WHOLE ROUTE MANIPULATION
     /// Reverses the route.
+    /// **Panics if the hop index is out of range**, see [`SourceRoutingHeader::try_reverse`].
     pub fn reverse(&mut self) {
         if self.is_empty() {
             return;
@@ -127,12 +223,33 @@ impl SourceRoutingHeader {
         self.hops.reverse();
         self.hop_index = self.hops.len() - self.hop_index - 1;
     }
+    /// Reverses the route, failing if the hop index is out of range.
+    /// An empty route is left untouched.
+    pub fn try_reverse(&mut self) -> Result<(), RoutingError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if !self.valid_hop_index() {
+            return Err(RoutingError::HopIndexOutOfRange {
+                hop_index: self.hop_index,
+                len: self.hops.len(),
+            });
+        }
+        self.reverse();
+        Ok(())
+    }
     /// Returns the reversed route.
     pub fn get_reversed(&self) -> SourceRoutingHeader {
         let mut clone = self.clone();
         clone.reverse();
         clone
     }
+    /// Returns the reversed route, failing if the hop index is out of range.
+    pub fn try_get_reversed(&self) -> Result<SourceRoutingHeader, RoutingError> {
+        let mut clone = self.clone();
+        clone.try_reverse()?;
+        Ok(clone)
+    }
     /// Extracts a sub-route from the route.
     /// If the range is decreasing, it also reverses the sub-route.
     pub fn sub_route(&self, range: impl RangeBounds<usize>) -> Option<SourceRoutingHeader> {
@@ -154,6 +271,7 @@ impl SourceRoutingHeader {
         })
     }
     /// Creates a new route without loops.
+    /// **Panics if the hop index is out of range**, see [`SourceRoutingHeader::try_without_loops`].
     pub fn without_loops(&self) -> SourceRoutingHeader {
         if self.is_empty() {
             return SourceRoutingHeader::empty_route();
@@ -185,9 +303,60 @@ impl SourceRoutingHeader {
 
         simplified
     }
+    /// Creates a new route without loops, failing if the hop index is out of range.
+    pub fn try_without_loops(&self) -> Result<SourceRoutingHeader, RoutingError> {
+        if !self.is_empty() && !self.valid_hop_index() {
+            return Err(RoutingError::HopIndexOutOfRange {
+                hop_index: self.hop_index,
+                len: self.hops.len(),
+            });
+        }
+        Ok(self.without_loops())
+    }
 
     // OTHERS
     pub fn len(&self) -> usize {
         self.hops.len()
     }
 }
+
+/// Builds a [`SourceRoutingHeader`] that is guaranteed to be valid:
+/// not empty, with a valid hop index and without loops or self hops.
+///
+/// `RouteBuilder::new(1).hop(11).hop(21).build()` gives \[ 1 ->(11)-> 21 ]
+#[derive(Debug, Clone)]
+pub struct RouteBuilder {
+    hops: Vec<NodeId>,
+    hop_index: usize,
+}
+
+impl RouteBuilder {
+    /// Starts a route from the given source.
+    /// **The hop index is set to 1.**
+    pub fn new(source: NodeId) -> Self {
+        Self {
+            hops: vec![source],
+            hop_index: 1,
+        }
+    }
+    /// Appends a hop to the route.
+    pub fn hop(mut self, hop: NodeId) -> Self {
+        self.hops.push(hop);
+        self
+    }
+    /// Appends some hops to the route.
+    pub fn hops(mut self, hops: impl IntoIterator<Item = NodeId>) -> Self {
+        self.hops.extend(hops);
+        self
+    }
+    /// Sets the hop index.
+    pub fn hop_index(mut self, hop_index: usize) -> Self {
+        self.hop_index = hop_index;
+        self
+    }
+    pub fn build(self) -> Result<SourceRoutingHeader, RoutingError> {
+        let route = SourceRoutingHeader::new(self.hops, self.hop_index);
+        route.validate()?;
+        Ok(route)
+    }
+}
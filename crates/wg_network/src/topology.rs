@@ -0,0 +1,277 @@
+use crate::{NodeId, SourceRoutingHeader};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeType {
+    Client,
+    Drone,
+    Server,
+}
+
+/// Bookkeeping of an undirected link between two nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeInfo {
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+    /// Last discovery round, counted by [`Topology::merge`], in which the edge was seen.
+    pub last_round: u64,
+}
+
+/// How [`Topology::merge`] treats what was not seen in the new round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Only what was seen in the new round is kept.
+    Replace,
+    /// Nothing is ever removed.
+    KeepAll,
+    /// Edges not seen in the last `n` rounds are removed, with the nodes left without edges.
+    AgeOut(u64),
+}
+
+/// Changes between two topologies, see [`Topology::diff`].
+/// All the lists are sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopologyDiff {
+    pub added_nodes: Vec<(NodeId, NodeType)>,
+    pub removed_nodes: Vec<(NodeId, NodeType)>,
+    /// Nodes whose type changed: (id, old type, new type).
+    pub changed_nodes: Vec<(NodeId, NodeType, NodeType)>,
+    pub added_edges: Vec<(NodeId, NodeId)>,
+    pub removed_edges: Vec<(NodeId, NodeId)>,
+}
+
+impl TopologyDiff {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+    /// Returns true if the route does not use any removed or changed node, nor any removed edge,
+    /// so it can be kept.
+    pub fn keeps_route(&self, route: &SourceRoutingHeader) -> bool {
+        let removed_node = |hop: &NodeId| {
+            self.removed_nodes.iter().any(|(id, _)| id == hop)
+                || self.changed_nodes.iter().any(|(id, _, _)| id == hop)
+        };
+        let removed_edge = |pair: &[NodeId]| {
+            self.removed_edges
+                .contains(&(pair[0].min(pair[1]), pair[0].max(pair[1])))
+        };
+        !route.hops.iter().any(removed_node) && !route.hops.windows(2).any(removed_edge)
+    }
+}
+
+/// Undirected graph of the network as known by a node.
+/// Edges are stored once, with the smaller id first.
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    nodes: HashMap<NodeId, NodeType>,
+    edges: HashMap<(NodeId, NodeId), EdgeInfo>,
+    round: u64,
+}
+
+/// This prints something like this:
+/// Topology { nodes: [1(Client), 11(Drone)], edges: [1-11] }
+impl Display for Topology {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut nodes = self.nodes.iter().collect::<Vec<_>>();
+        nodes.sort_by_key(|(id, _)| **id);
+        let mut edges = self.edges.keys().collect::<Vec<_>>();
+        edges.sort();
+        write!(
+            f,
+            "Topology {{ nodes: [{}], edges: [{}] }}",
+            nodes
+                .iter()
+                .map(|(id, node_type)| format!("{}({:?})", id, node_type))
+                .collect::<Vec<_>>()
+                .join(", "),
+            edges
+                .iter()
+                .map(|(a, b)| format!("{}-{}", a, b))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl Topology {
+    // INITIALIZATION
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // NODES
+    /// Adds a node or updates its type.
+    pub fn add_node(&mut self, id: NodeId, node_type: NodeType) {
+        self.nodes.insert(id, node_type);
+    }
+    /// Removes a node together with all its edges.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<NodeType> {
+        self.edges.retain(|&(a, b), _| a != id && b != id);
+        self.nodes.remove(&id)
+    }
+    /// Returns the type of the node if present.
+    pub fn node_type(&self, id: NodeId) -> Option<NodeType> {
+        self.nodes.get(&id).cloned()
+    }
+    /// Returns true if the node is present.
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+    /// Returns an iterator over the nodes and their types.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, NodeType)> + '_ {
+        self.nodes.iter().map(|(&id, &node_type)| (id, node_type))
+    }
+    /// Returns the number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // EDGES
+    /// Records the edge `a - b` as seen at `now`.
+    /// The first time an edge is seen both timestamps are set, afterward only `last_seen` is.
+    pub fn add_edge(&mut self, a: NodeId, b: NodeId, now: Instant) {
+        let round = self.round;
+        self.edges
+            .entry(Self::edge_key(a, b))
+            .and_modify(|info| {
+                info.last_seen = info.last_seen.max(now);
+                info.last_round = round;
+            })
+            .or_insert(EdgeInfo {
+                first_seen: now,
+                last_seen: now,
+                last_round: round,
+            });
+    }
+    /// Removes the edge `a - b` if present.
+    pub fn remove_edge(&mut self, a: NodeId, b: NodeId) -> Option<EdgeInfo> {
+        self.edges.remove(&Self::edge_key(a, b))
+    }
+    /// Returns the info about the edge `a - b` if present.
+    pub fn edge(&self, a: NodeId, b: NodeId) -> Option<&EdgeInfo> {
+        self.edges.get(&Self::edge_key(a, b))
+    }
+    /// Returns true if the edge `a - b` is present.
+    pub fn contains_edge(&self, a: NodeId, b: NodeId) -> bool {
+        self.edges.contains_key(&Self::edge_key(a, b))
+    }
+    /// Returns an iterator over the edges, the smaller id is always first.
+    pub fn edges(&self) -> impl Iterator<Item = ((NodeId, NodeId), &EdgeInfo)> + '_ {
+        self.edges.iter().map(|(&edge, info)| (edge, info))
+    }
+    /// Returns the number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+    /// Returns the neighbors of the node.
+    pub fn neighbors(&self, id: NodeId) -> HashSet<NodeId> {
+        self.edges
+            .keys()
+            .filter_map(|&(a, b)| match (a == id, b == id) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // ROUNDS
+    /// Returns what changed going from this topology to `other`.
+    pub fn diff(&self, other: &Topology) -> TopologyDiff {
+        let mut diff = TopologyDiff::default();
+        for (id, node_type) in other.nodes() {
+            match self.node_type(id) {
+                None => diff.added_nodes.push((id, node_type)),
+                Some(old_type) if old_type != node_type => {
+                    diff.changed_nodes.push((id, old_type, node_type))
+                }
+                _ => {}
+            }
+        }
+        diff.removed_nodes = self
+            .nodes()
+            .filter(|&(id, _)| !other.contains_node(id))
+            .collect();
+        diff.added_edges = other
+            .edges
+            .keys()
+            .filter(|edge| !self.edges.contains_key(edge))
+            .cloned()
+            .collect();
+        diff.removed_edges = self
+            .edges
+            .keys()
+            .filter(|edge| !other.edges.contains_key(edge))
+            .cloned()
+            .collect();
+
+        diff.added_nodes.sort_by_key(|&(id, _)| id);
+        diff.removed_nodes.sort_by_key(|&(id, _)| id);
+        diff.changed_nodes.sort_by_key(|&(id, _, _)| id);
+        diff.added_edges.sort();
+        diff.removed_edges.sort();
+        diff
+    }
+    /// Merges the topology discovered in a new round, starting the next round.
+    /// Returns what changed.
+    pub fn merge(&mut self, discovered: &Topology, policy: MergePolicy) -> TopologyDiff {
+        let before = self.clone();
+        self.round += 1;
+        let round = self.round;
+
+        for (id, node_type) in discovered.nodes() {
+            self.add_node(id, node_type);
+        }
+        for (edge, info) in discovered.edges() {
+            self.edges
+                .entry(edge)
+                .and_modify(|known| {
+                    known.first_seen = known.first_seen.min(info.first_seen);
+                    known.last_seen = known.last_seen.max(info.last_seen);
+                    known.last_round = round;
+                })
+                .or_insert(EdgeInfo {
+                    last_round: round,
+                    ..*info
+                });
+        }
+
+        let max_age = match policy {
+            MergePolicy::Replace => Some(1),
+            MergePolicy::KeepAll => None,
+            MergePolicy::AgeOut(n) => Some(n.max(1)),
+        };
+        if let Some(max_age) = max_age {
+            self.edges
+                .retain(|_, info| round - info.last_round < max_age);
+            let connected = self
+                .edges
+                .keys()
+                .flat_map(|&(a, b)| [a, b])
+                .collect::<HashSet<_>>();
+            self.nodes
+                .retain(|id, _| connected.contains(id) || discovered.contains_node(*id));
+        }
+        before.diff(self)
+    }
+    /// Returns the number of rounds merged so far.
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    // OTHERS
+    /// Returns true if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    fn edge_key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+        (a.min(b), a.max(b))
+    }
+}
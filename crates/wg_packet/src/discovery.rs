@@ -0,0 +1,121 @@
+use crate::{FloodRequest, FloodResponse, NodeType, Packet, PacketType};
+use std::time::{Duration, Instant};
+use wg_network::{NodeId, SourceRoutingHeader, Topology};
+
+/// One round of the Network Discovery Protocol, seen from the initiator.
+///
+/// The round produces the flood requests for the neighbors, collects the
+/// responses with its `flood_id` and is considered finished once no response
+/// arrived for `quiet_period`.
+#[derive(Debug, Clone)]
+pub struct DiscoveryRound {
+    flood_id: u64,
+    initiator_id: NodeId,
+    initiator_type: NodeType,
+    quiet_period: Duration,
+    last_activity: Instant,
+    topology: Topology,
+}
+
+impl DiscoveryRound {
+    // INITIALIZATION
+    pub fn new(
+        flood_id: u64,
+        initiator_id: NodeId,
+        initiator_type: NodeType,
+        quiet_period: Duration,
+    ) -> Self {
+        let mut topology = Topology::new();
+        topology.add_node(initiator_id, initiator_type);
+        Self {
+            flood_id,
+            initiator_id,
+            initiator_type,
+            quiet_period,
+            last_activity: Instant::now(),
+            topology,
+        }
+    }
+    /// Returns the flood request that starts this round.
+    pub fn flood_request(&self) -> FloodRequest {
+        FloodRequest::initialize(self.flood_id, self.initiator_id, self.initiator_type)
+    }
+    /// Returns the packets to send to each of the given neighbors to start the round.
+    /// This also restarts the quiet period.
+    pub fn start(
+        &mut self,
+        neighbors: impl IntoIterator<Item = NodeId>,
+        session_id: u64,
+    ) -> Vec<(NodeId, Packet)> {
+        self.last_activity = Instant::now();
+        let flood_request = self.flood_request();
+        neighbors
+            .into_iter()
+            .map(|neighbor| {
+                (
+                    neighbor,
+                    Packet::new_flood_request(
+                        SourceRoutingHeader::with_first_hop(vec![self.initiator_id, neighbor]),
+                        session_id,
+                        flood_request.clone(),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    // INGESTION
+    /// Records the path trace of the response if it belongs to this round.
+    /// Returns false if the response has a different `flood_id`.
+    pub fn handle_response(&mut self, flood_response: &FloodResponse) -> bool {
+        if flood_response.flood_id != self.flood_id {
+            return false;
+        }
+        let now = Instant::now();
+        self.last_activity = now;
+
+        // the path trace may or may not contain the initiator
+        let mut previous = match flood_response.path_trace.first() {
+            Some(&(id, _)) if id == self.initiator_id => None,
+            _ => Some(self.initiator_id),
+        };
+        for &(id, node_type) in flood_response.path_trace.iter() {
+            self.topology.add_node(id, node_type);
+            if let Some(previous) = previous.filter(|&previous| previous != id) {
+                self.topology.add_edge(previous, id, now);
+            }
+            previous = Some(id);
+        }
+        true
+    }
+    /// Like [`DiscoveryRound::handle_response`], but accepts any packet.
+    /// Returns false if the packet is not a response of this round.
+    pub fn handle_packet(&mut self, packet: &Packet) -> bool {
+        match &packet.pack_type {
+            PacketType::FloodResponse(flood_response) => self.handle_response(flood_response),
+            _ => false,
+        }
+    }
+
+    // STATUS
+    /// Returns true if no response arrived for the quiet period.
+    pub fn is_finished(&self) -> bool {
+        self.last_activity.elapsed() >= self.quiet_period
+    }
+    /// Returns how long to wait before the round can be considered finished.
+    pub fn remaining(&self) -> Duration {
+        self.quiet_period
+            .saturating_sub(self.last_activity.elapsed())
+    }
+    pub fn flood_id(&self) -> u64 {
+        self.flood_id
+    }
+    /// Returns the topology discovered so far.
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+    /// Ends the round returning the discovered topology.
+    pub fn finish(self) -> Topology {
+        self.topology
+    }
+}
@@ -2,12 +2,7 @@ use crate::Packet;
 use std::fmt::Display;
 use wg_network::{NodeId, SourceRoutingHeader};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum NodeType {
-    Client,
-    Drone,
-    Server,
-}
+pub use wg_network::NodeType;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "debug", derive(PartialEq))]
@@ -1,5 +1,11 @@
+mod discovery;
 mod flood;
 mod packet;
+mod route_cache;
+mod scheduler;
 
+pub use discovery::*;
 pub use flood::*;
 pub use packet::*;
+pub use route_cache::*;
+pub use scheduler::*;
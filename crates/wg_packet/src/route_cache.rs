@@ -0,0 +1,152 @@
+use crate::{NackType, Packet, PacketType};
+use std::collections::HashMap;
+use wg_network::{NodeId, SourceRoutingHeader, Topology};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl RouteCacheStats {
+    /// Returns the fraction of lookups that found a route, 0 if there were none.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+/// Routes of a host, keyed by destination.
+///
+/// Routes are evicted as soon as a Nack shows they cannot work anymore:
+/// - `ErrorInRouting(id)`: the link between the nacking drone and `id` is broken;
+/// - `UnexpectedRecipient(id)`: routes through `id` are broken;
+/// - `DestinationIsDrone`: routes ending at the nacking drone are wrong.
+///
+/// `Dropped` does not evict anything, since it only depends on the pdr.
+#[derive(Debug, Clone, Default)]
+pub struct RouteCache {
+    routes: HashMap<NodeId, SourceRoutingHeader>,
+    stats: RouteCacheStats,
+}
+
+impl RouteCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // LOOKUP
+    /// Returns the route to the destination if cached.
+    pub fn get(&mut self, destination: NodeId) -> Option<&SourceRoutingHeader> {
+        match self.routes.get(&destination) {
+            Some(route) => {
+                self.stats.hits += 1;
+                Some(route)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+    /// Returns the route to the destination, computing and caching it on a miss.
+    pub fn get_or_insert_with(
+        &mut self,
+        destination: NodeId,
+        compute: impl FnOnce() -> Option<SourceRoutingHeader>,
+    ) -> Option<&SourceRoutingHeader> {
+        if self.routes.contains_key(&destination) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            self.routes.insert(destination, compute()?);
+        }
+        self.routes.get(&destination)
+    }
+    /// Returns the route from `source` to the destination, computing the shortest one
+    /// over the topology on a miss.
+    pub fn route(
+        &mut self,
+        topology: &Topology,
+        source: NodeId,
+        destination: NodeId,
+    ) -> Option<&SourceRoutingHeader> {
+        self.get_or_insert_with(destination, || topology.shortest_route(source, destination))
+    }
+    pub fn insert(&mut self, destination: NodeId, route: SourceRoutingHeader) {
+        self.routes.insert(destination, route);
+    }
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+    pub fn stats(&self) -> RouteCacheStats {
+        self.stats
+    }
+
+    // INVALIDATION
+    /// Evicts the route to the destination.
+    pub fn invalidate(&mut self, destination: NodeId) -> bool {
+        self.evict_where(|&cached_destination, _| cached_destination == destination) > 0
+    }
+    /// Evicts the routes passing through the node, returning how many were evicted.
+    pub fn invalidate_node(&mut self, node: NodeId) -> usize {
+        self.evict_where(|_, route| route.hops.iter().skip(1).any(|&hop| hop == node))
+    }
+    /// Evicts the routes using the link `a - b` in either direction, returning how many were evicted.
+    pub fn invalidate_link(&mut self, a: NodeId, b: NodeId) -> usize {
+        self.evict_where(|_, route| {
+            route
+                .hops
+                .windows(2)
+                .any(|pair| (pair[0], pair[1]) == (a, b) || (pair[0], pair[1]) == (b, a))
+        })
+    }
+    /// Evicts the routes made useless by the Nack in the packet, returning how many were evicted.
+    /// Packets other than Nacks are ignored.
+    pub fn handle_nack(&mut self, packet: &Packet) -> usize {
+        let (PacketType::Nack(nack), Some(nacking)) =
+            (&packet.pack_type, packet.routing_header.source())
+        else {
+            return 0;
+        };
+        match nack.nack_type {
+            NackType::ErrorInRouting(id) => self.invalidate_link(nacking, id),
+            NackType::UnexpectedRecipient(id) => self.invalidate_node(id),
+            NackType::DestinationIsDrone => {
+                self.evict_where(|_, route| route.destination() == Some(nacking))
+            }
+            NackType::Dropped => 0,
+        }
+    }
+    /// Evicts the routes using nodes or links that are not in the topology anymore,
+    /// returning how many were evicted.
+    /// Call it with the topology of every new discovery round.
+    pub fn update_topology(&mut self, topology: &Topology) -> usize {
+        self.evict_where(|_, route| {
+            route.hops.iter().any(|&hop| !topology.contains_node(hop))
+                || route
+                    .hops
+                    .windows(2)
+                    .any(|pair| !topology.contains_edge(pair[0], pair[1]))
+        })
+    }
+    /// Evicts all the routes.
+    pub fn clear(&mut self) {
+        self.stats.evictions += self.routes.len() as u64;
+        self.routes.clear();
+    }
+    fn evict_where(&mut self, predicate: impl Fn(&NodeId, &SourceRoutingHeader) -> bool) -> usize {
+        let before = self.routes.len();
+        self.routes
+            .retain(|destination, route| !predicate(destination, route));
+        let evicted = before - self.routes.len();
+        self.stats.evictions += evicted as u64;
+        evicted
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{Fragment, Packet};
+use wg_network::{NodeId, SourceRoutingHeader};
+
+#[derive(Debug, Clone)]
+struct ScheduledRoute {
+    route: SourceRoutingHeader,
+    delivery_probability: f32,
+    credit: f32,
+}
+
+/// Spreads the fragments of a message over several routes, so that a single bad drone
+/// cannot stall the whole message.
+///
+/// Routes are picked with a smooth weighted round robin, the weight of each route
+/// being its estimated delivery probability: a route delivering twice as often gets
+/// twice the fragments, interleaved with the others instead of in bursts.
+#[derive(Debug, Clone, Default)]
+pub struct FragmentScheduler {
+    routes: Vec<ScheduledRoute>,
+}
+
+impl FragmentScheduler {
+    // INITIALIZATION
+    /// Creates a scheduler over the given routes and their delivery probabilities.
+    pub fn new(routes: impl IntoIterator<Item = (SourceRoutingHeader, f32)>) -> Self {
+        Self {
+            routes: routes
+                .into_iter()
+                .map(|(route, delivery_probability)| ScheduledRoute {
+                    route,
+                    delivery_probability: probability(delivery_probability),
+                    credit: 0.0,
+                })
+                .collect(),
+        }
+    }
+    /// Creates a scheduler over the given routes, estimating their delivery probability
+    /// from the (estimated) pdr of each drone.
+    pub fn with_pdr(
+        routes: impl IntoIterator<Item = SourceRoutingHeader>,
+        pdr: impl Fn(NodeId) -> f32,
+    ) -> Self {
+        Self::new(routes.into_iter().map(|route| {
+            let delivery_probability = Self::delivery_probability(&route, &pdr);
+            (route, delivery_probability)
+        }))
+    }
+    /// Returns the probability that a fragment crosses all the intermediate hops of the route.
+    /// A route through a drone with a pdr that is not a number never delivers.
+    pub fn delivery_probability(route: &SourceRoutingHeader, pdr: impl Fn(NodeId) -> f32) -> f32 {
+        let intermediate = route.hops.len().saturating_sub(1);
+        probability(
+            route
+                .hops
+                .iter()
+                .take(intermediate)
+                .skip(1)
+                .map(|&hop| 1.0 - pdr(hop).clamp(0.0, 1.0))
+                .product(),
+        )
+    }
+
+    // ROUTES
+    /// Updates the delivery probability of the route with the given index.
+    pub fn set_delivery_probability(&mut self, route_index: usize, delivery_probability: f32) {
+        if let Some(scheduled) = self.routes.get_mut(route_index) {
+            scheduled.delivery_probability = probability(delivery_probability);
+        }
+    }
+    /// Removes the routes passing through the given node, for example after a Nack naming it.
+    pub fn remove_routes_through(&mut self, node: NodeId) {
+        self.routes
+            .retain(|scheduled| !scheduled.route.contains(node));
+    }
+    /// Returns the routes and their delivery probabilities.
+    pub fn routes(&self) -> impl Iterator<Item = (&SourceRoutingHeader, f32)> {
+        self.routes
+            .iter()
+            .map(|scheduled| (&scheduled.route, scheduled.delivery_probability))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    // SCHEDULING
+    /// Returns the route for the next fragment.
+    pub fn next_route(&mut self) -> Option<&SourceRoutingHeader> {
+        // when no route is expected to deliver, all of them are tried equally
+        let uniform = self
+            .routes
+            .iter()
+            .all(|scheduled| scheduled.delivery_probability <= 0.0);
+        let weight = |scheduled: &ScheduledRoute| {
+            if uniform {
+                1.0
+            } else {
+                scheduled.delivery_probability
+            }
+        };
+        let total = self.routes.iter().map(weight).sum::<f32>();
+        for scheduled in self.routes.iter_mut() {
+            scheduled.credit += weight(scheduled);
+        }
+        let chosen = self
+            .routes
+            .iter_mut()
+            .max_by(|a, b| a.credit.total_cmp(&b.credit))?;
+        chosen.credit -= total;
+        Some(&chosen.route)
+    }
+    /// Creates the packets for the fragments, each with the route chosen for it.
+    pub fn schedule(
+        &mut self,
+        session_id: u64,
+        fragments: impl IntoIterator<Item = Fragment>,
+    ) -> Vec<Packet> {
+        fragments
+            .into_iter()
+            .map_while(|fragment| {
+                let route = self.next_route()?.clone();
+                Some(Packet::new_fragment(route, session_id, fragment))
+            })
+            .collect()
+    }
+}
+
+/// Clamps a probability to `0..=1`, taking NaN and infinities as 0,
+/// so that they cannot spoil the weights of the other routes.
+fn probability(value: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
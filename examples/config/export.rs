@@ -0,0 +1,16 @@
+/// this file showcases how to draw the network described by a config file,
+/// without having to install Graphviz
+///
+/// run it with `cargo run --example export --features toml` to get the DOT graph,
+/// or add `-- --svg` to get an SVG image
+use std::env;
+use wg_2024::config::Config;
+
+fn main() {
+    let config = Config::from_path("examples/config/config.toml").expect("Unable to load config");
+    if env::args().any(|arg| arg == "--svg") {
+        print!("{}", config.to_svg());
+    } else {
+        print!("{}", config.to_dot());
+    }
+}
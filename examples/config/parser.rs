@@ -1,16 +1,25 @@
 /// this file showcases an example of how you can parse the config found in the Network Initialization File inside some structs, which can then be used to initialize the network
 ///
-/// remember to add the Dependencies for toml to Cargo.toml:
-///
-/// [dependencies]
-/// toml = "0.8.19"
-use std::fs;
-use wg_2024::config::Config;
+/// the same config can also be written in JSON or YAML, with the `json` or `yaml` feature,
+/// the format is detected from the extension
+use wg_2024::config::{Config, ConfigFormat};
 
 fn main() {
-    let config_data =
-        fs::read_to_string("examples/config/config.toml").expect("Unable to read config file");
-    // having our structs implement the Deserialize trait allows us to use the toml::from_str function to deserialize the config file into each of them
-    let config: Config = toml::from_str(&config_data).expect("Unable to parse TOML");
+    // parse errors point to the line and column of the problem, and misspelled keys are rejected
+    let config = Config::from_path("examples/config/config.toml").unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1)
+    });
     println!("{:#?}", config);
+
+    // a config can also be written back, for example after generating or modifying it
+    let written = config.to_toml_string();
+    println!("{}", written);
+    let parsed = Config::parse(&written, ConfigFormat::Toml).expect("Unable to parse written TOML");
+    assert_eq!(
+        parsed,
+        config.sorted(),
+        "parse -> write -> parse is not lossless"
+    );
+    assert_eq!(parsed.to_toml_string(), written);
 }
@@ -0,0 +1,110 @@
+/// this file showcases the web browser: it classifies the servers it discovers, lists the files
+/// of the text servers and fetches a document with the media it references
+///
+/// here the browser is directly connected to the servers, in the simulation there are drones in between
+use crossbeam_channel::unbounded;
+use std::collections::HashMap;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use wg_2024::host::{
+    BrowserCommand, BrowserEvent, MediaServer, ServerRuntime, TextServer, Transport,
+    TransportOptions, WebBrowser,
+};
+use wg_2024::message::file_id;
+use wg_2024::network::NodeType;
+
+fn main() {
+    let (browser_id, text_id, media_id) = (1, 20, 30);
+    let root = std::env::temp_dir().join("wg_2024_browser");
+    let (text_dir, media_dir) = (root.join("text"), root.join("media"));
+    fs::create_dir_all(&text_dir).unwrap();
+    fs::create_dir_all(&media_dir).unwrap();
+    fs::write(
+        text_dir.join("cats.md"),
+        "# Cats\n![a cat](media:cat.png)\nthe dog is elsewhere: media:dog.jpg.",
+    )
+    .unwrap();
+    fs::write(media_dir.join("cat.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+    let (browser_send, browser_recv) = unbounded();
+    let (text_send, text_recv) = unbounded();
+    let (media_send, media_recv) = unbounded();
+    let (event_send, _event_recv) = unbounded();
+    let (_command_send, command_recv) = unbounded();
+
+    let text_server = TextServer::new(&text_dir).unwrap();
+    let (controller_send, controller_recv) = (event_send.clone(), command_recv.clone());
+    let packet_send = HashMap::from([(browser_id, browser_send.clone())]);
+    thread::spawn(move || {
+        ServerRuntime::new(
+            text_id,
+            text_server,
+            controller_send,
+            controller_recv,
+            text_recv,
+            packet_send,
+        )
+        .run();
+    });
+    let media_server = MediaServer::new(&media_dir).unwrap();
+    let (controller_send, controller_recv) = (event_send.clone(), command_recv.clone());
+    let packet_send = HashMap::from([(browser_id, browser_send)]);
+    thread::spawn(move || {
+        ServerRuntime::new(
+            media_id,
+            media_server,
+            controller_send,
+            controller_recv,
+            media_recv,
+            packet_send,
+        )
+        .run();
+    });
+
+    let (browser_command_send, browser_command_recv) = unbounded();
+    let (browser_event_send, browser_event_recv) = unbounded();
+    let transport = Transport::new(
+        browser_id,
+        NodeType::Client,
+        event_send,
+        HashMap::from([(text_id, text_send), (media_id, media_send)]),
+        TransportOptions::default(),
+    );
+    let mut browser = WebBrowser::new(
+        transport,
+        command_recv,
+        browser_recv,
+        browser_command_recv,
+        browser_event_send,
+    );
+    thread::spawn(move || browser.run());
+
+    // commands wait for the servers to be classified
+    browser_command_send
+        .send(BrowserCommand::ListFiles)
+        .unwrap();
+    browser_command_send
+        .send(BrowserCommand::Fetch(file_id("cats.md")))
+        .unwrap();
+    while let Ok(event) = browser_event_recv.recv_timeout(Duration::from_secs(5)) {
+        match event {
+            BrowserEvent::Document(document) => {
+                println!(
+                    "Document from server {}:\n{}",
+                    document.server, document.text
+                );
+                for (id, media) in document.media {
+                    println!(
+                        "Media {}: {} of {} bytes",
+                        id, media.content_type, media.size
+                    );
+                }
+                println!("Missing media: {:?}", document.missing);
+                break;
+            }
+            event => println!("Browser event {:?}", event),
+        }
+    }
+    let _ = fs::remove_dir_all(root);
+}
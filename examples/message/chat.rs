@@ -0,0 +1,92 @@
+/// this file showcases the chat client: two clients register to a chat server and one of
+/// them sends a message to the other, forwarded by the server
+///
+/// here the clients are directly connected to the server, in the simulation there are drones in between
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use wg_2024::controller::HostCommand;
+use wg_2024::host::{
+    ChatClient, ChatCommand, ChatEvent, ChatServer, ServerRuntime, Transport, TransportOptions,
+};
+use wg_2024::network::{NodeId, NodeType};
+use wg_2024::packet::Packet;
+
+fn spawn_client(
+    id: NodeId,
+    server: (NodeId, Sender<Packet>),
+    packet_recv: Receiver<Packet>,
+) -> (Sender<ChatCommand>, Receiver<ChatEvent>) {
+    let (controller_send, _controller_recv) = unbounded();
+    // the client stops when the controller closes its channel, it is kept open here
+    let (command_send, controller_recv) = unbounded::<HostCommand>();
+    let (chat_command_send, chat_command_recv) = unbounded();
+    let (chat_event_send, chat_event_recv) = unbounded();
+    thread::spawn(move || {
+        let _command_send = command_send;
+        let transport = Transport::new(
+            id,
+            NodeType::Client,
+            controller_send,
+            HashMap::from([server]),
+            TransportOptions::default(),
+        );
+        ChatClient::new(
+            transport,
+            controller_recv,
+            packet_recv,
+            chat_command_recv,
+            chat_event_send,
+        )
+        .run();
+    });
+    (chat_command_send, chat_event_recv)
+}
+
+fn main() {
+    let server_id = 10;
+    let (server_send, server_recv) = unbounded();
+    let (alice_send, alice_recv) = unbounded();
+    let (bob_send, bob_recv) = unbounded();
+    let (event_send, _event_recv) = unbounded();
+    let (_command_send, command_recv) = unbounded();
+    thread::spawn(move || {
+        ServerRuntime::new(
+            server_id,
+            ChatServer::new(),
+            event_send,
+            command_recv,
+            server_recv,
+            HashMap::from([(1, alice_send), (2, bob_send)]),
+        )
+        .run();
+    });
+
+    let (alice, alice_events) = spawn_client(1, (server_id, server_send.clone()), alice_recv);
+    let (bob, bob_events) = spawn_client(2, (server_id, server_send), bob_recv);
+
+    // commands wait for the servers to be classified
+    bob.send(ChatCommand::Register(server_id)).unwrap();
+    let event = bob_events.recv_timeout(Duration::from_secs(5)).unwrap();
+    println!("Bob: {:?}", event);
+    let event = bob_events.recv_timeout(Duration::from_secs(5)).unwrap();
+    println!("Bob: {:?}", event);
+
+    alice.send(ChatCommand::Register(server_id)).unwrap();
+    for (to, message) in [(2, "Hello Bob"), (3, "Hello Carol")] {
+        let message = message.to_string();
+        let command = ChatCommand::SendMessage {
+            server: server_id,
+            to,
+            message,
+        };
+        alice.send(command).unwrap();
+    }
+    for _ in 0..4 {
+        let event = alice_events.recv_timeout(Duration::from_secs(5)).unwrap();
+        println!("Alice: {:?}", event);
+    }
+    let event = bob_events.recv_timeout(Duration::from_secs(5)).unwrap();
+    println!("Bob: {:?}", event);
+}
@@ -0,0 +1,77 @@
+/// this file compares the encodings and the compression of the messages: how many bytes and
+/// fragments typical messages take, each fragment being one more chance to be dropped by a drone
+use wg_2024::message::{
+    ChatRequest, Compression, DroneSend, MediaFile, MediaResponse, MessageCodec, MessageEncoding,
+    TextResponse, DEFAULT_MAX_MESSAGE_SIZE,
+};
+
+/// Bytes looking like compressed media, as image and video files are.
+fn media_bytes(size: usize) -> Vec<u8> {
+    let mut state: u32 = 0x2024;
+    (0..size)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+fn row<M: DroneSend>(codecs: &mut [(&str, MessageCodec)], name: &str, content: &M) {
+    print!("{:<20}", name);
+    for (_, codec) in codecs.iter_mut() {
+        let fragments = codec.fragment(content).unwrap().len();
+        print!("{:>20}", fragments);
+    }
+    println!();
+}
+
+fn main() {
+    let mut codecs = [
+        ("json", MessageEncoding::Json, Compression::None),
+        ("binary", MessageEncoding::Binary, Compression::None),
+        (
+            "binary + deflate",
+            MessageEncoding::Binary,
+            Compression::Deflate(6),
+        ),
+    ]
+    .map(|(name, encoding, compression)| {
+        let mut codec = MessageCodec::with_encoding(DEFAULT_MAX_MESSAGE_SIZE, encoding);
+        codec.set_compression(compression);
+        (name, codec)
+    });
+
+    println!("fragments per message");
+    print!("{:<20}", "message");
+    for (name, _) in codecs.iter() {
+        print!("{:>20}", name);
+    }
+    println!();
+    let chat = ChatRequest::SendMessage {
+        from: 1,
+        to: 2,
+        message: "Hello, how are you?".to_string(),
+    };
+    row(&mut codecs, "chat message", &chat);
+    let document = "# Cats\n![a cat](media:cat.png)\n".repeat(128);
+    row(&mut codecs, "4 KiB document", &TextResponse::Text(document));
+    for (name, content_type, size) in [
+        ("16 KiB image", "image/png", 16 * 1024),
+        ("256 KiB image", "image/jpeg", 256 * 1024),
+        ("1 MiB video", "video/mp4", 1024 * 1024),
+    ] {
+        let media = MediaFile::new(content_type, media_bytes(size));
+        row(&mut codecs, name, &MediaResponse::Media(media));
+    }
+
+    // media are already compressed, they are sent as they are
+    let stats = codecs[2].1.stats();
+    println!(
+        "\ndeflate compressed {} of {} messages, saving {} bytes and {} fragments ({:.1}% of the size)",
+        stats.compressed_messages,
+        stats.messages,
+        stats.bytes_saved(),
+        stats.fragments_saved(),
+        (1.0 - stats.ratio()) * 100.0
+    );
+}
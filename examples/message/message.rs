@@ -1,84 +0,0 @@
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
-use wg_2024::network::NodeId;
-
-#[derive(Debug, Clone)]
-pub struct Message<M: DroneSend> {
-    pub source_id: NodeId,
-    pub session_id: u64,
-    pub content: M,
-}
-
-pub trait DroneSend: Serialize + DeserializeOwned {
-    fn stringify(&self) -> String {
-        serde_json::to_string(self).unwrap()
-    }
-    fn from_string(raw: String) -> Result<Self, String> {
-        serde_json::from_str(raw.as_str()).map_err(|e| e.to_string())
-    }
-}
-
-pub trait Request: DroneSend {}
-pub trait Response: DroneSend {}
-
-// ReqServerType,
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TextRequest {
-    TextList,
-    Text(u64),
-}
-
-impl DroneSend for TextRequest {}
-impl Request for TextRequest {}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum MediaRequest {
-    MediaList,
-    Media(u64),
-}
-
-impl DroneSend for MediaRequest {}
-impl Request for MediaRequest {}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ChatRequest {
-    ClientList,
-    Register(NodeId),
-    SendMessage {
-        from: NodeId,
-        to: NodeId,
-        message: String,
-    },
-}
-
-impl DroneSend for ChatRequest {}
-impl Request for ChatRequest {}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TextResponse {
-    TextList(Vec<u64>),
-    Text(String),
-    NotFound,
-}
-
-impl DroneSend for TextResponse {}
-impl Response for TextResponse {}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum MediaResponse {
-    MediaList(Vec<u64>),
-    Media(Vec<u8>), // should we use some other type?
-}
-
-impl DroneSend for MediaResponse {}
-impl Response for MediaResponse {}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ChatResponse {
-    ClientList(Vec<NodeId>),
-    MessageFrom { from: NodeId, message: Vec<u8> },
-    MessageSent,
-}
-
-impl DroneSend for ChatResponse {}
-impl Response for ChatResponse {}
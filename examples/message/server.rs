@@ -1,95 +1,93 @@
-mod message;
+/// this file showcases how to run a server: the Server trait holds the logic of the server,
+/// here the ChatServer of the library, and a ServerRuntime handles floods, fragments, Acks
+/// and retransmissions
+///
+/// here the client is directly connected to the server, in the simulation there are drones in between
+use crossbeam_channel::unbounded;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+use wg_2024::host::{ChatServer, ServerRuntime, Transport, TransportEvent, TransportOptions};
+use wg_2024::message::{ChatRequest, ChatResponse, ServerTypeRequest, ServerTypeResponse};
+use wg_2024::network::NodeType;
 
-use crate::message::{ChatRequest, ChatResponse, DroneSend, Message, Request, Response};
-use wg_2024::network::topology::ServerType;
-use wg_2024::network::*;
+fn main() {
+    let (client_id, server_id) = (1, 10);
+    let (client_send, client_recv) = unbounded();
+    let (server_send, server_recv) = unbounded();
+    let (event_send, _event_recv) = unbounded();
+    let (_command_send, command_recv) = unbounded();
 
-pub trait Server {
-    type RequestType: Request;
-    type ResponseType: Response;
+    let server_event_send = event_send.clone();
+    thread::spawn(move || {
+        ServerRuntime::new(
+            server_id,
+            ChatServer::new(),
+            server_event_send,
+            command_recv,
+            server_recv,
+            HashMap::from([(client_id, client_send)]),
+        )
+        .run();
+    });
 
-    fn compose_message(
-        source_id: NodeId,
-        session_id: u64,
-        raw_content: String,
-    ) -> Result<Message<Self::RequestType>, String> {
-        let content = Self::RequestType::from_string(raw_content)?;
-        Ok(Message {
-            session_id,
-            source_id,
-            content,
-        })
-    }
+    let mut client = Transport::new(
+        client_id,
+        NodeType::Client,
+        event_send,
+        HashMap::from([(server_id, server_send)]),
+        TransportOptions::default(),
+    );
+    client.discover();
+    let requests = [
+        ChatRequest::Register(client_id),
+        ChatRequest::ClientList,
+        // the server forwards the message back to the client, the only one registered
+        ChatRequest::SendMessage {
+            from: client_id,
+            to: client_id,
+            message: "Hello".to_string(),
+        },
+        ChatRequest::SendMessage {
+            from: client_id,
+            to: 2,
+            message: "Hello".to_string(),
+        },
+    ];
+    // every request is answered, plus `server_type?` and the forwarded message
+    let mut remaining = requests.len() + 2;
+    let mut handshake = None;
 
-    fn on_request_arrived(&mut self, source_id: NodeId, session_id: u64, raw_content: String) {
-        if raw_content == "ServerType" {
-            let _server_type = Self::get_sever_type();
-            // send response
-            return;
-        }
-        match Self::compose_message(source_id, session_id, raw_content) {
-            Ok(message) => {
-                let response = self.handle_request(message.content);
-                self.send_response(response);
+    let start = Instant::now();
+    while remaining > 0 && start.elapsed() < Duration::from_secs(5) {
+        if let Ok(packet) = client_recv.recv_timeout(client.next_timeout()) {
+            for event in client.handle_packet(packet) {
+                if let TransportEvent::Received {
+                    session_id,
+                    payload,
+                    ..
+                } = event
+                {
+                    if handshake == Some(session_id) {
+                        let ServerTypeResponse(server_type) =
+                            client.codec().decode(&payload).unwrap();
+                        println!("Client found a {} server", server_type);
+                    } else {
+                        let response: ChatResponse = client.codec().decode(&payload).unwrap();
+                        println!("Client received {:?}", response);
+                    }
+                    remaining -= 1;
+                }
             }
-            Err(str) => panic!("{}", str),
         }
-    }
-
-    fn send_response(&mut self, _response: Self::ResponseType) {
-        // send response
-    }
-
-    fn handle_request(&mut self, request: Self::RequestType) -> Self::ResponseType;
-
-    fn get_sever_type() -> ServerType;
-}
-
-pub(crate) struct ChatServer;
-
-impl Server for ChatServer {
-    type RequestType = ChatRequest;
-    type ResponseType = ChatResponse;
-
-    fn handle_request(&mut self, request: Self::RequestType) -> Self::ResponseType {
-        match request {
-            ChatRequest::ClientList => {
-                println!("Sending ClientList");
-                ChatResponse::ClientList(vec![1, 2])
-            }
-            ChatRequest::Register(id) => {
-                println!("Registering {}", id);
-                ChatResponse::ClientList(vec![1, 2])
-            }
-            ChatRequest::SendMessage {
-                message,
-                to,
-                from: _,
-            } => {
-                println!("Sending message \"{}\" to {}", message, to);
-                // effectively forward message
-                ChatResponse::MessageSent
+        for event in client.tick() {
+            // the requests are sent once the server was discovered
+            if let (TransportEvent::TopologyChanged(_), None) = (event, handshake) {
+                handshake = Some(client.send_message(server_id, &ServerTypeRequest).unwrap());
+                for request in requests.iter() {
+                    client.send_message(server_id, request).unwrap();
+                }
             }
         }
     }
-
-    fn get_sever_type() -> ServerType {
-        ServerType::Chat
-    }
-}
-
-fn main() {
-    let mut server = ChatServer;
-    server.on_request_arrived(1, 1, ChatRequest::Register(1).stringify());
-    server.on_request_arrived(
-        1,
-        1,
-        ChatRequest::SendMessage {
-            from: 1,
-            to: 2,
-            message: "Hello".to_string(),
-        }
-        .stringify(),
-    );
-    server.on_request_arrived(1, 1, "ServerType".to_string());
 }
@@ -1,4 +1,4 @@
-use wg_2024::network::SourceRoutingHeader;
+use wg_2024::network::{RouteBuilder, SourceRoutingHeader};
 use wg_2024::packet::{FloodRequest, Fragment, NodeType, Packet};
 
 fn main() {
@@ -23,9 +23,9 @@ fn main() {
             .unwrap_or("None".to_string())
     );
     println!(
-        "sub_route(2..0): {}",
+        "sub_route(2..5): {}",
         route
-            .sub_route(2..0)
+            .sub_route(2..5)
             .map(|r| r.to_string())
             .unwrap_or("None".to_string())
     );
@@ -34,6 +34,34 @@ fn main() {
         SourceRoutingHeader::new(vec![0, 1, 2, 3, 1, 4], 0).without_loops()
     );
 
+    println!("----- Checked SourceRoutingHeader -----");
+    let mut untrusted = SourceRoutingHeader::new(vec![1, 2, 3], 7);
+    println!("untrusted: {:?}", untrusted);
+    println!("validate: {:?}", untrusted.validate());
+    println!("try_reverse: {:?}", untrusted.try_reverse());
+    println!("try_without_loops: {:?}", untrusted.try_without_loops());
+    untrusted.reset_hop_index();
+    println!(
+        "try_decrease_hop_index: {:?}",
+        untrusted.try_decrease_hop_index()
+    );
+    println!(
+        "RouteBuilder::new(1).hops([2, 3]).build(): {:?}",
+        RouteBuilder::new(1).hops([2, 3]).build()
+    );
+    println!(
+        "RouteBuilder::new(1).hops([2, 3, 2]).build(): {:?}",
+        RouteBuilder::new(1).hops([2, 3, 2]).build()
+    );
+    let looping = SourceRoutingHeader::initialize(vec![0, 1, 2, 1, 4]);
+    println!(
+        "{}: has_loops: {}, contains(2): {}, position_of(1): {:?}",
+        looping,
+        looping.has_loops(),
+        looping.contains(2),
+        looping.position_of(1)
+    );
+
     println!("----- Fragment -----");
     let packet = Packet::new_fragment(
         route.clone(),